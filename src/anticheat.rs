@@ -7,13 +7,14 @@ use valence::{
         living::Health,
         player::Food,
     },
-    prelude::{Added, OnInsert, OnRemove, Query, Trigger},
+    prelude::{Added, EventReader, OnInsert, OnRemove, Query, Trigger, With},
     status_effects::StatusEffect,
     uuid::Uuid,
     GameMode,
 };
 
 use crate::perms::OperMode;
+use crate::round::RoundStarted;
 
 //Not really an anticheat, I just couldn't think of a better name for what this does.
 //The goal of this plugin is to disable jumping and set health to a lower amount.
@@ -25,9 +26,12 @@ use crate::perms::OperMode;
 //movement speed is then increased slightly just to make it less boring to move around.
 pub struct AnticheatPlugin;
 
+/// The health every player has when they are at full health.
+pub const MAX_HEALTH: f32 = 6.0;
+
 impl Plugin for AnticheatPlugin {
     fn build(&self, app: &mut valence::prelude::App) {
-        app.add_systems(Update, setup)
+        app.add_systems(Update, (setup, reset_on_round_start))
             .observe(gm_mode_enable)
             .observe(gm_mode_disable);
     }
@@ -47,14 +51,30 @@ fn setup(
     for (mut attributes, mut hp, mut food, mut statuses) in &mut clients {
         disable_jump(&mut statuses);
 
-        hp.0 = 6.0f32;
+        hp.0 = MAX_HEALTH;
         food.0 = 0;
 
-        attributes.set_base_value(EntityAttribute::GenericMaxHealth, 6.0);
+        attributes.set_base_value(EntityAttribute::GenericMaxHealth, MAX_HEALTH as f64);
         attributes.set_add_modifier(EntityAttribute::GenericMovementSpeed, Uuid::nil(), 0.03);
     }
 }
 
+//Everyone starts a round on full health, regardless of what happened in the lobby.
+fn reset_on_round_start(
+    mut clients: Query<(&mut Health, &mut Food), With<Client>>,
+    mut events: EventReader<RoundStarted>,
+) {
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+
+    for (mut hp, mut food) in &mut clients {
+        hp.0 = MAX_HEALTH;
+        food.0 = 0;
+    }
+}
+
 fn disable_jump(statuses: &mut ActiveStatusEffects) {
     statuses.apply(
         ActiveStatusEffect::from_effect(StatusEffect::JumpBoost)
@@ -0,0 +1,179 @@
+use dan_world::DanWorld;
+use valence::{
+    entity::living::Health,
+    prelude::*,
+    title::SetTitle,
+    world_border::{WorldBorderBundle, WorldBorderCenter, WorldBorderLerp},
+};
+
+use crate::anticheat::MAX_HEALTH;
+use crate::markers::{MapMarkers, Marker, Region};
+use crate::perms::OperMode;
+use crate::spectator::Spectator;
+use crate::teams::Team;
+
+pub struct ArenaPlugin;
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ArenaSettings>()
+            .add_systems(Update, (init_border, init_clients, enforce_bounds));
+    }
+}
+
+/// The playable area of the map. Without this resource, players can go anywhere.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct ArenaBounds(pub Region);
+
+impl ArenaBounds {
+    /// Uses the `"arena"` region of the map if there is one, otherwise the area
+    /// covered by the world's chunks. Everything below `base_y` is the void.
+    pub fn from_world(world: &DanWorld, base_y: f64, markers: &MapMarkers) -> Self {
+        if let Some(region) = markers.region("arena") {
+            return Self(*region);
+        }
+
+        Self(Region::new(
+            DVec3::new(0.0, base_y, 0.0),
+            DVec3::new(
+                world.width as f64 * 16.0 - 1.0,
+                f64::MAX,
+                world.depth as f64 * 16.0 - 1.0,
+            ),
+        ))
+    }
+}
+
+/// What happens to players outside of the arena.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LeaveAction {
+    /// Put them back where they last were inside the arena.
+    TeleportBack,
+    /// Hurt them every second they stay outside. Instead of dying, they're sent back to spawn.
+    Damage(f32),
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct ArenaSettings {
+    pub leave_action: LeaveAction,
+}
+
+impl Default for ArenaSettings {
+    fn default() -> Self {
+        Self {
+            leave_action: LeaveAction::TeleportBack,
+        }
+    }
+}
+
+//The last position a player was at inside the arena.
+#[derive(Component, Debug)]
+struct LastInside(DVec3);
+
+fn init_border(
+    layers: Query<Entity, (With<ChunkLayer>, Without<WorldBorderCenter>)>,
+    bounds: Option<Res<ArenaBounds>>,
+    mut commands: Commands,
+) {
+    let Some(bounds) = bounds else {
+        return;
+    };
+
+    //The border can only be a square, so it's sized to fit the longest side.
+    //Players are still held to the actual bounds by enforce_bounds.
+    let Region { min, max } = bounds.0;
+    let diameter = (max.x - min.x + 1.0).max(max.z - min.z + 1.0);
+
+    for layer in &layers {
+        commands.entity(layer).insert(WorldBorderBundle {
+            center: WorldBorderCenter {
+                x: (min.x + max.x + 1.0) / 2.0,
+                z: (min.z + max.z + 1.0) / 2.0,
+            },
+            lerp: WorldBorderLerp {
+                target_diameter: diameter,
+                current_diameter: diameter,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+    }
+}
+
+fn init_clients(clients: Query<(Entity, &Position), Added<Client>>, mut commands: Commands) {
+    for (client, pos) in &clients {
+        commands.entity(client).insert(LastInside(pos.0));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn enforce_bounds(
+    mut players: Query<
+        (
+            &mut Client,
+            &mut Position,
+            &mut Look,
+            &mut Health,
+            &mut LastInside,
+            Option<&Team>,
+        ),
+        (Without<Spectator>, Without<OperMode>),
+    >,
+    bounds: Option<Res<ArenaBounds>>,
+    settings: Res<ArenaSettings>,
+    markers: Res<MapMarkers>,
+    server: Res<Server>,
+) {
+    let Some(bounds) = bounds else {
+        return;
+    };
+
+    let once_per_second = server.current_tick() % server.tick_rate().get() as i64 == 0;
+
+    for (mut client, mut pos, mut look, mut hp, mut last, team) in &mut players {
+        if bounds.0.contains(pos.0) {
+            last.0 = pos.0;
+            continue;
+        }
+
+        let spawn = match team {
+            Some(team) => markers.team_spawns(*team)[0],
+            None => markers.lobby_spawn(),
+        };
+
+        //There's no coming back from the void, so fallers always go back to spawn.
+        if pos.0.y < bounds.0.min.y {
+            send_back(&spawn, &mut pos, &mut look, &mut hp, &mut last);
+            continue;
+        }
+
+        match settings.leave_action {
+            LeaveAction::TeleportBack => {
+                pos.set(last.0);
+                client.set_action_bar("You can't leave the arena.");
+            }
+            LeaveAction::Damage(damage) if once_per_second => {
+                if hp.0 > damage {
+                    hp.0 -= damage;
+                    client.set_action_bar("Get back into the arena!");
+                } else {
+                    send_back(&spawn, &mut pos, &mut look, &mut hp, &mut last);
+                }
+            }
+            LeaveAction::Damage(_) => {}
+        }
+    }
+}
+
+//Players sent back are healed, so they don't come back already on their last legs.
+fn send_back(
+    spawn: &Marker,
+    pos: &mut Position,
+    look: &mut Look,
+    hp: &mut Health,
+    last: &mut LastInside,
+) {
+    spawn.teleport(pos, look);
+    hp.0 = MAX_HEALTH;
+    last.0 = spawn.pos;
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use valence::{
+    message::SendMessage,
+    prelude::*,
+    protocol::{packets::play::BlockBreakingProgressS2c, VarInt, WritePacket},
+};
+
+use crate::connections;
+use crate::economy::Gold;
+use crate::ownership::{BlockOwnership, PlacedBlock};
+use crate::physics::BlockUpdates;
+use crate::round::RoundState;
+use crate::teams::Team;
+
+pub struct BlockHealthPlugin;
+
+impl Plugin for BlockHealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlockHealthSettings>()
+            .add_event::<FarmBlockDestroyed>()
+            .add_systems(
+                Update,
+                (init_layers, (damage_farm_blocks, notify_destroyed).chain()),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct BlockHealthSettings {
+    /// Hit points of each kind of farm block. Unlisted blocks use `default_hp`.
+    pub hit_points: HashMap<BlockKind, u32>,
+    pub default_hp: u32,
+    /// Damage dealt by a single golem hit.
+    pub golem_damage: u32,
+    /// Gold awarded to the golem that destroys a farm block.
+    pub bounty: Option<u32>,
+}
+
+impl Default for BlockHealthSettings {
+    fn default() -> Self {
+        Self {
+            hit_points: HashMap::from([
+                (BlockKind::WhiteWool, 2),
+                (BlockKind::OakPlanks, 4),
+                (BlockKind::OakFence, 4),
+                (BlockKind::OakFenceGate, 4),
+                (BlockKind::OakDoor, 5),
+                (BlockKind::HayBlock, 6),
+                (BlockKind::Cobblestone, 10),
+                (BlockKind::Sand, 3),
+                (BlockKind::Gravel, 3),
+            ]),
+            default_hp: 3,
+            golem_damage: 1,
+            bounty: Some(1),
+        }
+    }
+}
+
+impl BlockHealthSettings {
+    pub fn hp_of(&self, kind: BlockKind) -> u32 {
+        self.hit_points
+            .get(&kind)
+            .copied()
+            .unwrap_or(self.default_hp)
+    }
+}
+
+/// Damage taken by the player-placed blocks of a [`ChunkLayer`]. Undamaged blocks are not stored.
+#[derive(Component, Debug, Default)]
+pub struct BlockDamage {
+    //Damage is stored alongside the tick the block was placed on, so a block
+    //placed where a damaged one used to be starts out undamaged.
+    damage: HashMap<BlockPos, (i64, u32)>,
+}
+
+impl BlockDamage {
+    pub fn get(&self, pos: BlockPos, block: &PlacedBlock) -> u32 {
+        match self.damage.get(&pos) {
+            Some(&(placed_at, taken)) if placed_at == block.placed_at => taken,
+            _ => 0,
+        }
+    }
+
+    pub fn set(&mut self, pos: BlockPos, block: &PlacedBlock, taken: u32) {
+        self.damage.insert(pos, (block.placed_at, taken));
+    }
+
+    pub fn clear(&mut self, pos: BlockPos) {
+        self.damage.remove(&pos);
+    }
+
+    /// Forgets the damage of every block, and takes the cracks off of them.
+    pub fn clear_all(&mut self, layer: &mut ChunkLayer) {
+        for (pos, _) in self.damage.drain() {
+            set_crack(layer, pos, u8::MAX);
+        }
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct FarmBlockDestroyed {
+    pub pos: BlockPos,
+    pub block: PlacedBlock,
+    pub golem: Entity,
+}
+
+fn init_layers(layers: Query<Entity, Added<ChunkLayer>>, mut commands: Commands) {
+    for layer in &layers {
+        commands.entity(layer).insert(BlockDamage::default());
+    }
+}
+
+//Every click a golem lands on a sheep's block counts as one hit.
+fn damage_farm_blocks(
+    mut golems: Query<(&Team, Option<&mut Gold>), With<Client>>,
+    mut layers: Query<(
+        &mut ChunkLayer,
+        &mut BlockOwnership,
+        &mut BlockDamage,
+        &mut BlockUpdates,
+    )>,
+    mut events: EventReader<DiggingEvent>,
+    mut ew: EventWriter<FarmBlockDestroyed>,
+    settings: Res<BlockHealthSettings>,
+    round: Res<RoundState>,
+    server: Res<Server>,
+) {
+    let Ok((mut layer, mut owners, mut damage, mut updates)) = layers.get_single_mut() else {
+        return;
+    };
+
+    for event in events.read() {
+        if event.state != DiggingState::Start || *round != RoundState::Hunt {
+            continue;
+        }
+
+        let Ok((Team::Golem, gold)) = golems.get_mut(event.client) else {
+            continue;
+        };
+
+        let Some(block) = owners.get(event.position).copied() else {
+            continue;
+        };
+
+        if block.team != Some(Team::Sheep) {
+            continue;
+        }
+
+        let hp = settings.hp_of(block.kind);
+        let taken = damage.get(event.position, &block) + settings.golem_damage;
+
+        if taken < hp {
+            damage.set(event.position, &block, taken);
+            set_crack(&mut layer, event.position, (taken * 10 / hp) as u8);
+            continue;
+        }
+
+        damage.clear(event.position);
+        owners.forget(event.position);
+        set_crack(&mut layer, event.position, u8::MAX);
+        layer.set_block(event.position, BlockState::AIR);
+        connections::update_around(&mut layer, event.position);
+        updates.schedule_around(event.position, server.current_tick() + 1);
+
+        if let (Some(bounty), Some(mut gold)) = (settings.bounty, gold) {
+            gold.0 += bounty;
+        }
+
+        ew.send(FarmBlockDestroyed {
+            pos: event.position,
+            block,
+            golem: event.client,
+        });
+    }
+}
+
+//Stages 0 through 9 are displayed, anything else removes the animation.
+fn set_crack(layer: &mut ChunkLayer, pos: BlockPos, stage: u8) {
+    //The animation is keyed by entity id. Deriving it from the position gives every
+    //block its own animation, no matter how many golems are hitting it.
+    let id = i32::MIN | (pos.x.wrapping_mul(31).wrapping_add(pos.y).wrapping_mul(31) ^ pos.z);
+
+    layer
+        .view_writer(pos)
+        .write_packet(&BlockBreakingProgressS2c {
+            entity_id: VarInt(id),
+            position: pos,
+            destroy_stage: stage,
+        });
+}
+
+fn notify_destroyed(
+    mut events: EventReader<FarmBlockDestroyed>,
+    mut clients: Query<(&mut Client, &UniqueId)>,
+    names: Query<&Username>,
+) {
+    for FarmBlockDestroyed { pos, block, golem } in events.read() {
+        let Some((mut owner, _)) = clients.iter_mut().find(|(_, uuid)| uuid.0 == block.owner)
+        else {
+            continue;
+        };
+
+        let golem = names
+            .get(*golem)
+            .map(|ign| ign.0.as_str())
+            .unwrap_or("A golem");
+
+        owner.send_chat_message(format!(
+            "{golem} destroyed your {} at {}, {}, {}.",
+            block.kind.to_str(),
+            pos.x,
+            pos.y,
+            pos.z
+        ));
+    }
+}
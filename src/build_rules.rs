@@ -0,0 +1,79 @@
+use valence::prelude::*;
+
+use crate::markers::{MapMarkers, Region};
+
+pub struct BuildRulesPlugin;
+
+impl Plugin for BuildRulesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BuildRules>();
+    }
+}
+
+/// Where players outside of op mode may not place blocks. Combines the `"no_build"`
+/// regions and `"build_limit"` marker of the map with these settings.
+#[derive(Resource, Debug, Clone)]
+pub struct BuildRules {
+    /// Extra no-build regions on top of the ones defined by the map.
+    pub zones: Vec<Region>,
+    /// Nothing can be placed above this Y. Overrides the `"build_limit"` marker of the map.
+    pub max_y: Option<i32>,
+    /// How far around each golem spawn is kept clear.
+    pub golem_spawn_radius: f64,
+    /// How far around the cage is kept clear, at any height above its floor.
+    pub cage_radius: f64,
+}
+
+impl Default for BuildRules {
+    fn default() -> Self {
+        Self {
+            zones: vec![],
+            max_y: None,
+            golem_spawn_radius: 4.0,
+            cage_radius: 4.0,
+        }
+    }
+}
+
+impl BuildRules {
+    /// Returns why a block can't be placed at `pos`, if it can't.
+    pub fn check(&self, pos: BlockPos, markers: &MapMarkers) -> Result<(), &'static str> {
+        let center = DVec3::new(pos.x as f64, pos.y as f64, pos.z as f64) + 0.5;
+
+        let max_y = self
+            .max_y
+            .or_else(|| markers.point("build_limit").map(|limit| limit.pos.y as i32));
+        if max_y.is_some_and(|max_y| pos.y > max_y) {
+            return Err("You can't build this high.");
+        }
+
+        if markers
+            .points("golem_spawn")
+            .iter()
+            .any(|spawn| spawn.pos.distance(center) <= self.golem_spawn_radius)
+        {
+            return Err("You can't build in the golem spawn.");
+        }
+
+        if markers.regions("cage").iter().any(|cage| {
+            let min = cage.min.floor().xz() - self.cage_radius;
+            let max = cage.max.floor().xz() + 1.0 + self.cage_radius;
+            center.y >= cage.min.y.floor()
+                && center.xz().cmpge(min).all()
+                && center.xz().cmplt(max).all()
+        }) {
+            return Err("You can't build on the golem cage.");
+        }
+
+        if markers
+            .regions("no_build")
+            .iter()
+            .chain(&self.zones)
+            .any(|zone| zone.contains_block(pos))
+        {
+            return Err("You can't build here.");
+        }
+
+        Ok(())
+    }
+}
@@ -1,18 +1,221 @@
+use std::collections::HashMap;
+
 use valence::{
-    entity::entity::Flags, interact_block::InteractBlockEvent, inventory::HeldItem, prelude::*,
+    entity::entity::Flags,
+    interact_block::InteractBlockEvent,
+    inventory::HeldItem,
+    log,
+    nbt::compound,
+    prelude::*,
+    protocol::{
+        packets::play::BlockUpdateS2c,
+        sound::{Sound, SoundCategory},
+        WritePacket,
+    },
+    title::SetTitle,
 };
 
+use crate::arena::ArenaBounds;
+use crate::build_rules::BuildRules;
+use crate::cage::cage_center;
+use crate::connections;
+use crate::disguise::Disguise;
+use crate::economy::{EconomySettings, Gold};
+use crate::history::{BlockChange, EditHistory};
+use crate::markers::{MapMarkers, Region};
+use crate::ownership::{BlockOwnership, PlacedBlock};
 use crate::perms::OperMode;
+use crate::physics::{self, BlockUpdates};
+use crate::placement;
+use crate::reachability::{self, ReachabilitySettings};
+use crate::round::RoundState;
+use crate::tagging::Tagged;
+use crate::teams::{JoinTeamEvent, LeaveTeamEvent, Team};
 
 pub struct BuildingPlugin;
 
 impl Plugin for BuildingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (block_place, block_break));
+        app.init_resource::<FarmPalette>()
+            .init_resource::<BreakSettings>()
+            .add_systems(
+                Update,
+                (
+                    //Filling a bucket must not let the same click pour it back out.
+                    block_place.before(physics::fill_buckets),
+                    block_break,
+                    give_farm_kit,
+                    take_farm_kit,
+                    warn_without_golem_spawns.run_if(resource_changed::<MapMarkers>),
+                ),
+            );
+    }
+}
+
+/// Restrictions on where a farm block may be placed by a sheep.
+#[derive(Debug, Clone, Copy)]
+pub struct FarmBlockRule {
+    /// The block must be placed on top of another block.
+    pub needs_support: bool,
+    /// How many blocks of this kind may be stacked on top of each other.
+    pub max_stack: u32,
+}
+
+/// The blocks sheep are allowed to place in survival. Players in [`OperMode`]
+/// are not limited by this. Sheep are handed the blocks in this order.
+#[derive(Resource, Debug, Clone)]
+pub struct FarmPalette {
+    rules: Vec<(BlockKind, FarmBlockRule)>,
+}
+
+impl Default for FarmPalette {
+    fn default() -> Self {
+        let wall = FarmBlockRule {
+            needs_support: false,
+            max_stack: 3,
+        };
+        let grounded = FarmBlockRule {
+            needs_support: true,
+            max_stack: 1,
+        };
+        //Sand and gravel would fall anyway, so they need something to land on.
+        let falling = FarmBlockRule {
+            needs_support: true,
+            max_stack: 3,
+        };
+
+        Self {
+            rules: vec![
+                (BlockKind::WhiteWool, wall),
+                (BlockKind::OakPlanks, wall),
+                (BlockKind::Cobblestone, wall),
+                (BlockKind::OakFence, grounded),
+                (BlockKind::OakFenceGate, grounded),
+                (BlockKind::OakDoor, grounded),
+                (BlockKind::HayBlock, grounded),
+                (BlockKind::Sand, falling),
+                (BlockKind::Gravel, falling),
+                (BlockKind::Water, grounded),
+            ],
+        }
+    }
+}
+
+impl FarmPalette {
+    pub fn rule(&self, kind: BlockKind) -> Option<&FarmBlockRule> {
+        self.rules
+            .iter()
+            .find(|(other, _)| *other == kind)
+            .map(|(_, rule)| rule)
+    }
+
+    /// Replaces the rule of `kind`, or adds it to the end of the palette.
+    pub fn insert(&mut self, kind: BlockKind, rule: FarmBlockRule) {
+        match self.rules.iter_mut().find(|(other, _)| *other == kind) {
+            Some((_, old)) => *old = rule,
+            None => self.rules.push((kind, rule)),
+        }
+    }
+
+    pub fn remove(&mut self, kind: BlockKind) {
+        self.rules.retain(|(other, _)| *other != kind);
+    }
+
+    pub fn kinds(&self) -> impl Iterator<Item = BlockKind> + '_ {
+        self.rules.iter().map(|(kind, _)| *kind)
+    }
+}
+
+/// How long blocks take to break in survival. Players may only break blocks placed
+/// by their own team, unless they are in [`OperMode`].
+#[derive(Resource, Debug, Clone)]
+pub struct BreakSettings {
+    /// Seconds it takes to break each kind of block with a speed of 1.
+    pub break_secs: HashMap<BlockKind, f32>,
+    /// Used for blocks without an entry in `break_secs`.
+    pub default_break_secs: f32,
+    /// How fast each team breaks blocks. 2.0 breaks blocks in half the time.
+    pub team_speed: HashMap<Team, f32>,
+    /// How much sooner than expected a block may be finished, to make up for lag.
+    pub tolerance: f32,
+}
+
+impl Default for BreakSettings {
+    fn default() -> Self {
+        //Roughly what vanilla takes by hand, so the client agrees on when a block is done.
+        Self {
+            break_secs: HashMap::from([
+                (BlockKind::WhiteWool, 1.2),
+                (BlockKind::OakPlanks, 3.0),
+                (BlockKind::Cobblestone, 10.0),
+                (BlockKind::OakFence, 3.0),
+                (BlockKind::OakFenceGate, 3.0),
+                (BlockKind::OakDoor, 4.5),
+                (BlockKind::HayBlock, 0.75),
+                (BlockKind::Sand, 0.75),
+                (BlockKind::Gravel, 0.9),
+            ]),
+            default_break_secs: 1.0,
+            team_speed: HashMap::from([(Team::Sheep, 1.0), (Team::Golem, 1.0)]),
+            tolerance: 0.7,
+        }
+    }
+}
+
+impl BreakSettings {
+    /// How many ticks it takes to break `kind` at the given speed.
+    pub fn break_ticks(&self, kind: BlockKind, speed: f32, server: &Server) -> i64 {
+        let secs = self
+            .break_secs
+            .get(&kind)
+            .copied()
+            .unwrap_or(self.default_break_secs);
+
+        (secs * server.tick_rate().get() as f32 / speed).ceil() as i64
+    }
+}
+
+/// Makes a player break blocks faster. Upgrades stack with the speed of their team.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct DigSpeed(pub f32);
+
+//The block a player started breaking in survival.
+#[derive(Component, Debug)]
+struct Digging {
+    pos: BlockPos,
+    started_at: i64,
+}
+
+impl FarmBlockRule {
+    fn allows(&self, layer: &ChunkLayer, pos: BlockPos, kind: BlockKind) -> bool {
+        let below = |n: i32| {
+            layer
+                .block(BlockPos::new(pos.x, pos.y - n, pos.z))
+                .map(|block| block.state)
+                .unwrap_or(BlockState::AIR)
+        };
+
+        if self.needs_support && below(1).is_air() {
+            return false;
+        }
+
+        let stacked = (1..=self.max_stack as i32)
+            .take_while(|&n| below(n).to_kind() == kind)
+            .count() as u32;
+
+        stacked < self.max_stack
     }
 }
 
-fn try_open(layer: &mut ChunkLayer, event: &InteractBlockEvent, flags: &Flags) -> bool {
+fn try_open(
+    layer: &mut ChunkLayer,
+    owners: &BlockOwnership,
+    event: &InteractBlockEvent,
+    client: &mut Client,
+    flags: &Flags,
+    team: Option<&Team>,
+    is_op: bool,
+) -> bool {
     //Sneaking always overrides opening things with building.
     if flags.sneaking() {
         return false;
@@ -23,37 +226,201 @@ fn try_open(layer: &mut ChunkLayer, event: &InteractBlockEvent, flags: &Flags) -
     };
 
     let state = block.state;
-    if !state.to_kind().props().contains(&PropName::Open) {
+    let kind = state.to_kind();
+    if !kind.props().contains(&PropName::Open) {
+        return false;
+    }
+
+    //Iron doors and trapdoors only open with redstone, which admins get to skip.
+    if matches!(kind, BlockKind::IronDoor | BlockKind::IronTrapdoor) && !is_op {
         return false;
     }
 
-    layer.set_block(
-        event.position,
-        match state.get(PropName::Open) {
-            Some(PropValue::True) => state.set(PropName::Open, PropValue::False),
-            Some(PropValue::False) => state.set(PropName::Open, PropValue::True),
-            _ => return false,
-        },
-    );
+    let open = match state.get(PropName::Open) {
+        Some(PropValue::True) => false,
+        Some(PropValue::False) => true,
+        _ => return false,
+    };
+
+    let mut toggled = with_other_half(layer, event.position);
+    if let Some(partner) = double_door_partner(layer, event.position, state) {
+        toggled.extend(with_other_half(layer, partner));
+    }
+
+    //Doors and gates built by sheep keep everyone else out. Golems have to break them.
+    //Every half is checked, so a sheep door can't be opened through a map door next to it.
+    let sheep_only = toggled.iter().any(|&pos| {
+        owners
+            .get(pos)
+            .is_some_and(|placed| placed.team == Some(Team::Sheep))
+    });
+    if sheep_only && team != Some(&Team::Sheep) && !is_op {
+        client.set_action_bar("Only sheep can open this.");
+        return true;
+    }
+
+    let value = if open {
+        PropValue::True
+    } else {
+        PropValue::False
+    };
+
+    for pos in toggled {
+        if let Some(block) = layer.block(pos) {
+            let state = block.state.set(PropName::Open, value);
+            layer.set_block(pos, state);
+        }
+    }
+
+    if let Some(sound) = toggle_sound(kind, open) {
+        let center = DVec3::new(
+            event.position.x as f64,
+            event.position.y as f64,
+            event.position.z as f64,
+        ) + 0.5;
+        layer.play_sound(sound, SoundCategory::Block, center, 1.0, 1.0);
+    }
 
     true
 }
 
+//The door next to this one that makes up a double door with it: same kind, facing
+//and half, hinged on the other side and opened or closed the same way.
+fn double_door_partner(layer: &ChunkLayer, pos: BlockPos, state: BlockState) -> Option<BlockPos> {
+    if !state.to_kind().to_str().ends_with("_door") {
+        return None;
+    }
+
+    let facing = placement::prop_val_to_dir(state.get(PropName::Facing)?)?;
+    let (side, other_hinge) = match state.get(PropName::Hinge)? {
+        PropValue::Right => (placement::counter_clockwise(facing), PropValue::Left),
+        _ => (
+            placement::opposite(placement::counter_clockwise(facing)),
+            PropValue::Right,
+        ),
+    };
+
+    let partner = pos.get_in_direction(side);
+    let other = layer.block(partner)?.state;
+    let pairs = other.to_kind() == state.to_kind()
+        && other.get(PropName::Facing) == state.get(PropName::Facing)
+        && other.get(PropName::Half) == state.get(PropName::Half)
+        && other.get(PropName::Open) == state.get(PropName::Open)
+        && other.get(PropName::Hinge) == Some(other_hinge);
+
+    pairs.then_some(partner)
+}
+
+//The block at `pos`, and the other half of it if it's a door.
+fn with_other_half(layer: &ChunkLayer, pos: BlockPos) -> Vec<BlockPos> {
+    let Some(state) = layer.block(pos).map(|block| block.state) else {
+        return vec![];
+    };
+
+    let other = match state.get(PropName::Half) {
+        Some(PropValue::Lower) => BlockPos::new(pos.x, pos.y + 1, pos.z),
+        Some(PropValue::Upper) => BlockPos::new(pos.x, pos.y - 1, pos.z),
+        _ => return vec![pos],
+    };
+
+    if layer
+        .block(other)
+        .is_some_and(|block| block.state.to_kind() == state.to_kind())
+    {
+        vec![pos, other]
+    } else {
+        vec![pos]
+    }
+}
+
+fn toggle_sound(kind: BlockKind, open: bool) -> Option<Sound> {
+    let name = kind.to_str();
+    let iron = name.starts_with("iron_");
+
+    let sound = if name.ends_with("_trapdoor") {
+        match (iron, open) {
+            (true, true) => Sound::BlockIronTrapdoorOpen,
+            (true, false) => Sound::BlockIronTrapdoorClose,
+            (false, true) => Sound::BlockWoodenTrapdoorOpen,
+            (false, false) => Sound::BlockWoodenTrapdoorClose,
+        }
+    } else if name.ends_with("_door") {
+        match (iron, open) {
+            (true, true) => Sound::BlockIronDoorOpen,
+            (true, false) => Sound::BlockIronDoorClose,
+            (false, true) => Sound::BlockWoodenDoorOpen,
+            (false, false) => Sound::BlockWoodenDoorClose,
+        }
+    } else if name.ends_with("_fence_gate") {
+        if open {
+            Sound::BlockFenceGateOpen
+        } else {
+            Sound::BlockFenceGateClose
+        }
+    } else {
+        return None;
+    };
+
+    Some(sound)
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn block_place(
-    mut clients: Query<(&HeldItem, &Inventory, &Flags), (With<Client>, With<OperMode>)>,
-    mut layers: Query<&mut ChunkLayer>,
+    mut clients: Query<
+        (
+            &mut Client,
+            &HeldItem,
+            &mut Inventory,
+            &Flags,
+            &Look,
+            &GameMode,
+            &UniqueId,
+            Has<OperMode>,
+            Option<&Team>,
+            Option<&mut Gold>,
+            Has<Tagged>,
+            Option<&mut EditHistory>,
+        ),
+        With<Client>,
+    >,
+    bodies: Query<(&Position, &GameMode, Option<&Disguise>, Option<&Team>), With<Client>>,
+    mut layers: Query<(&mut ChunkLayer, &mut BlockOwnership, &mut BlockUpdates)>,
     mut events: EventReader<InteractBlockEvent>,
+    palette: Res<FarmPalette>,
+    economy: Res<EconomySettings>,
+    rules: Res<BuildRules>,
+    markers: Res<MapMarkers>,
+    reach: Res<ReachabilitySettings>,
+    bounds: Option<Res<ArenaBounds>>,
+    round: Res<RoundState>,
+    server: Res<Server>,
 ) {
-    let mut layer = layers.single_mut();
+    let Ok((mut layer, mut owners, mut updates)) = layers.get_single_mut() else {
+        return;
+    };
 
     for event in events.read() {
-        let Ok((held, inv, flags)) = clients.get_mut(event.client) else {
+        let Ok((
+            mut client,
+            held,
+            mut inv,
+            flags,
+            look,
+            gm,
+            uuid,
+            is_op,
+            team,
+            gold,
+            tagged,
+            history,
+        )) = clients.get_mut(event.client)
+        else {
             continue;
         };
 
         //Try to open the block that was interacted with. If this
         //returns true, the block was openable
-        if try_open(&mut layer, &event, flags) {
+        if try_open(&mut layer, &owners, &event, &mut client, flags, team, is_op) {
             continue;
         }
 
@@ -61,43 +428,457 @@ fn block_place(
             continue;
         }
 
-        let stack = inv.slot(held.slot());
+        let stack = inv.slot(held.slot()).clone();
         if stack.is_empty() {
             continue;
         }
 
-        let Some(block) = BlockKind::from_item_kind(stack.item) else {
+        let Some(block) = block_for(stack.item) else {
             continue;
         };
 
-        let place_pos = event.position.get_in_direction(event.face);
-        let state = block.to_state().set(
-            PropName::Axis,
-            match event.face {
-                Direction::Down | Direction::Up => PropValue::Y,
-                Direction::North | Direction::South => PropValue::Z,
-                Direction::West | Direction::East => PropValue::X,
-            },
+        let blocks = placement::place(
+            &layer,
+            block,
+            event.position,
+            event.face,
+            event.cursor_pos,
+            look,
+        );
+        let Some(&(place_pos, _)) = blocks.first() else {
+            continue;
+        };
+
+        //Admins build freely. Everyone else has to be a sheep in a running round, and tagged
+        //sheep are spirits, which can't build.
+        if !is_op
+            && (team != Some(&Team::Sheep)
+                || tagged
+                || *gm != GameMode::Survival
+                || !round.is_running())
+        {
+            continue;
+        }
+
+        let is_air = |pos: BlockPos| layer.block(pos).is_some_and(|block| block.state.is_air());
+        //The rest of a door or bed needs room too, even for admins.
+        if !blocks[1..].iter().all(|&(pos, _)| is_air(pos)) {
+            continue;
+        }
+
+        //Nobody gets to trap players by building inside of them, not even admins.
+        if blocks
+            .iter()
+            .any(|&(pos, state)| state.blocks_motion() && inside_player(&bodies, pos))
+        {
+            client.set_action_bar("There's someone in the way.");
+            continue;
+        }
+
+        //Sheep are held to the farm rules.
+        if !is_op {
+            let Some(rule) = palette.rule(block) else {
+                continue;
+            };
+
+            if !is_air(place_pos) || !rule.allows(&layer, place_pos, block) {
+                continue;
+            }
+
+            if let Err(reason) = blocks
+                .iter()
+                .try_for_each(|&(pos, _)| rules.check(pos, &markers))
+            {
+                client.set_action_bar(reason);
+                continue;
+            }
+
+            let arena = bounds.as_deref().map(|bounds| &bounds.0);
+            if walls_off(
+                &layer, &owners, &bodies, &blocks, &markers, &economy, &reach, arena,
+            ) {
+                client.set_action_bar("Golems must still be able to reach every sheep and farm.");
+                continue;
+            }
+
+            let cost = economy.cost_of(block);
+            if !gold.is_some_and(|mut gold| gold.try_spend(cost)) {
+                client.set_action_bar(format!("You need {cost} gold to place that."));
+                continue;
+            }
+
+            if matches!(stack.item, ItemKind::WaterBucket | ItemKind::LavaBucket) {
+                //Kit buckets stay kit buckets.
+                inv.set_slot(held.slot(), ItemStack::new(ItemKind::Bucket, 1, stack.nbt));
+            } else if stack.count > 1 {
+                inv.set_slot_amount(held.slot(), stack.count - 1);
+            } else {
+                inv.set_slot(held.slot(), ItemStack::EMPTY);
+            }
+        }
+
+        let mut edit = vec![];
+        for &(pos, state) in &blocks {
+            let placed = PlacedBlock {
+                owner: uuid.0,
+                team: team.copied(),
+                placed_at: server.current_tick(),
+                kind: block,
+            };
+
+            if let Some(before) = layer.block(pos).map(|block| block.state) {
+                edit.push(BlockChange {
+                    pos,
+                    before,
+                    after: state,
+                    placed_before: owners.get(pos).copied(),
+                    placed_after: Some(placed),
+                });
+            }
+
+            layer.set_block(pos, state);
+            owners.record(pos, placed);
+        }
+
+        for &(pos, _) in &blocks {
+            connections::update_around(&mut layer, pos);
+            updates.schedule_around(pos, server.current_tick() + 1);
+        }
+
+        if let Some(mut history) = history.filter(|_| is_op) {
+            history.record(edit);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn inside_player(
+    bodies: &Query<(&Position, &GameMode, Option<&Disguise>, Option<&Team>), With<Client>>,
+    pos: BlockPos,
+) -> bool {
+    let block_min = DVec3::new(pos.x as f64, pos.y as f64, pos.z as f64);
+    let block_max = block_min + 1.0;
+
+    bodies.iter().any(|(body, gm, disguise, _)| {
+        if *gm == GameMode::Spectator {
+            return false;
+        }
+
+        //The server moves the player's own body, which is still there under the disguise.
+        let (player_width, player_height) = Disguise::hitbox(None);
+        let (width, height) = Disguise::hitbox(disguise);
+        let (width, height) = (width.max(player_width), height.max(player_height));
+        let min = body.0 - DVec3::new(width / 2.0, 0.0, width / 2.0);
+        let max = body.0 + DVec3::new(width / 2.0, height, width / 2.0);
+
+        min.cmplt(block_max).all() && max.cmpgt(block_min).all()
+    })
+}
+
+//Sheep may not seal themselves or their farms away from the golems.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn walls_off(
+    layer: &ChunkLayer,
+    owners: &BlockOwnership,
+    bodies: &Query<(&Position, &GameMode, Option<&Disguise>, Option<&Team>), With<Client>>,
+    blocks: &[(BlockPos, BlockState)],
+    markers: &MapMarkers,
+    economy: &EconomySettings,
+    reach: &ReachabilitySettings,
+    bounds: Option<&Region>,
+) -> bool {
+    let block_at = |pos: DVec3| {
+        BlockPos::new(
+            pos.x.floor() as i32,
+            pos.y.floor() as i32,
+            pos.z.floor() as i32,
+        )
+    };
+
+    let placed: Vec<_> = blocks
+        .iter()
+        .filter(|(_, state)| state.blocks_motion())
+        .map(|&(pos, _)| pos)
+        .collect();
+    if placed.is_empty() {
+        return false;
+    }
+
+    //Maps without golem spawns send golems to the lobby spawn, but the cage is a better
+    //guess of where they'll be let out.
+    let starts: Vec<_> = match markers.region("cage") {
+        Some(cage) if markers.points("golem_spawn").is_empty() => vec![block_at(cage_center(cage))],
+        _ => markers
+            .team_spawns(Team::Golem)
+            .iter()
+            .map(|spawn| block_at(spawn.pos))
+            .collect(),
+    };
+
+    let sheep = bodies
+        .iter()
+        .filter(|&(_, gm, _, team)| *gm != GameMode::Spectator && team == Some(&Team::Sheep))
+        .map(|(pos, ..)| block_at(pos.0));
+    let farms = owners
+        .iter()
+        .filter(|(_, block)| {
+            block.team == Some(Team::Sheep) && economy.farm_income.contains_key(&block.kind)
+        })
+        .map(|(pos, _)| pos);
+    let targets: Vec<_> = sheep.chain(farms).collect();
+
+    reachability::seals_off(layer, reach, bounds, &starts, &targets, &placed)
+}
+
+//Builds are only checked for walling off sheep from where golems start.
+fn warn_without_golem_spawns(markers: Res<MapMarkers>) {
+    if markers.points("golem_spawn").is_empty() {
+        let from = if markers.region("cage").is_some() {
+            "cage"
+        } else {
+            "lobby spawn"
+        };
+        log::warn!(
+            "This map has no golem spawns. Sheep builds are checked from the {from} instead."
         );
+    }
+}
 
-        layer.set_block(place_pos, state);
+//Sheep are handed a stack of every farm block when they join.
+fn give_farm_kit(
+    mut clients: Query<&mut Inventory, With<Client>>,
+    mut events: EventReader<JoinTeamEvent>,
+    palette: Res<FarmPalette>,
+) {
+    for event in events.read() {
+        if event.team != Team::Sheep {
+            continue;
+        }
+
+        let Ok(mut inv) = clients.get_mut(event.entity) else {
+            continue;
+        };
+
+        //Slots 36..45 are the hotbar, whatever doesn't fit goes in the slots above it.
+        for (slot, kind) in (36..45).chain(9..36).zip(palette.kinds()) {
+            let item = item_for(kind);
+            //Buckets don't stack.
+            let count = if item.to_str().ends_with("bucket") {
+                1
+            } else {
+                64
+            };
+            inv.set_slot(slot, kit_stack(item, count));
+        }
     }
 }
 
+//Kit items are tagged, so they can be told apart from the same items picked up elsewhere.
+const KIT_TAG: &str = "sheeptag_farm_kit";
+
+fn kit_stack(item: ItemKind, count: i8) -> ItemStack {
+    ItemStack::new(item, count, Some(compound! { KIT_TAG => true }))
+}
+
+fn is_kit(stack: &ItemStack) -> bool {
+    stack
+        .nbt
+        .as_ref()
+        .is_some_and(|nbt| nbt.contains_key(KIT_TAG))
+}
+
+//Sheep that leave their team don't get to keep building with what they were given.
+fn take_farm_kit(
+    mut clients: Query<&mut Inventory, With<Client>>,
+    mut events: EventReader<LeaveTeamEvent>,
+) {
+    for event in events.read() {
+        if event.team != Team::Sheep {
+            continue;
+        }
+
+        let Ok(mut inv) = clients.get_mut(event.entity) else {
+            continue;
+        };
+
+        for slot in 0..inv.slot_count() {
+            if is_kit(inv.slot(slot)) {
+                inv.set_slot(slot, ItemStack::EMPTY);
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
 fn block_break(
-    clients: Query<&GameMode, With<OperMode>>,
-    mut layers: Query<&mut ChunkLayer>,
+    mut clients: Query<(
+        &mut Client,
+        &GameMode,
+        Option<&Team>,
+        Option<&DigSpeed>,
+        Option<&Digging>,
+        Has<OperMode>,
+        Has<Tagged>,
+        Option<&mut EditHistory>,
+    )>,
+    mut layers: Query<(&mut ChunkLayer, &mut BlockOwnership, &mut BlockUpdates)>,
     mut events: EventReader<DiggingEvent>,
+    settings: Res<BreakSettings>,
+    server: Res<Server>,
+    mut commands: Commands,
 ) {
-    let mut layer = layers.single_mut();
+    let Ok((mut layer, mut owners, mut updates)) = layers.get_single_mut() else {
+        return;
+    };
+    let now = server.current_tick();
 
     for event in events.read() {
-        let Ok(gm) = clients.get(event.client) else {
+        let Ok((mut client, gm, team, upgrade, digging, is_op, tagged, mut history)) =
+            clients.get_mut(event.client)
+        else {
+            continue;
+        };
+
+        let Some(state) = layer.block(event.position).map(|block| block.state) else {
             continue;
         };
 
-        if *gm == GameMode::Creative && event.state == DiggingState::Start {
-            layer.set_block(event.position, BlockState::AIR);
+        //Admins can undo whatever they break.
+        let mut record = |edit: Vec<BlockChange>| {
+            if let Some(history) = history.as_mut().filter(|_| is_op) {
+                history.record(edit);
+            }
+        };
+
+        if is_op && *gm == GameMode::Creative {
+            if event.state == DiggingState::Start {
+                record(break_block(
+                    &mut layer,
+                    &mut owners,
+                    &mut updates,
+                    event.position,
+                    now,
+                ));
+            }
+            continue;
+        }
+
+        if *gm != GameMode::Survival {
+            continue;
         }
+
+        let own_block = owners
+            .get(event.position)
+            .is_some_and(|placed| team.is_some() && placed.team.as_ref() == team);
+        if !is_op && (!own_block || tagged) {
+            //The client removes blocks it thinks it broke, so it needs to be told otherwise.
+            if event.state != DiggingState::Abort {
+                resend_block(&mut client, event.position, state);
+            }
+            continue;
+        }
+
+        let speed = team
+            .and_then(|team| settings.team_speed.get(team))
+            .copied()
+            .unwrap_or(1.0)
+            * upgrade.map_or(1.0, |upgrade| upgrade.0);
+        let ticks = settings.break_ticks(state.to_kind(), speed, &server);
+
+        match event.state {
+            DiggingState::Start if ticks <= 0 => {
+                record(break_block(
+                    &mut layer,
+                    &mut owners,
+                    &mut updates,
+                    event.position,
+                    now,
+                ));
+            }
+            DiggingState::Start => {
+                commands.entity(event.client).insert(Digging {
+                    pos: event.position,
+                    started_at: now,
+                });
+            }
+            DiggingState::Abort => {
+                commands.entity(event.client).remove::<Digging>();
+            }
+            DiggingState::Stop => {
+                commands.entity(event.client).remove::<Digging>();
+
+                let finished = digging.is_some_and(|digging| {
+                    let elapsed = now - digging.started_at;
+                    digging.pos == event.position
+                        && elapsed as f32 >= ticks as f32 * settings.tolerance
+                });
+
+                if finished {
+                    record(break_block(
+                        &mut layer,
+                        &mut owners,
+                        &mut updates,
+                        event.position,
+                        now,
+                    ));
+                } else {
+                    resend_block(&mut client, event.position, state);
+                }
+            }
+        }
+    }
+}
+
+fn resend_block(client: &mut Client, pos: BlockPos, state: BlockState) {
+    client.write_packet(&BlockUpdateS2c {
+        position: pos,
+        block_id: state,
+    });
+}
+
+//Removes a block along with the other half of doors and the like. Returns what was removed.
+fn break_block(
+    layer: &mut ChunkLayer,
+    owners: &mut BlockOwnership,
+    updates: &mut BlockUpdates,
+    pos: BlockPos,
+    now: i64,
+) -> Vec<BlockChange> {
+    let mut edit = vec![];
+
+    for pos in with_other_half(layer, pos) {
+        let placed = owners.forget(pos);
+        if let Some(before) = layer.block(pos).map(|block| block.state) {
+            edit.push(BlockChange {
+                pos,
+                before,
+                after: BlockState::AIR,
+                placed_before: placed,
+                placed_after: None,
+            });
+        }
+
+        layer.set_block(pos, BlockState::AIR);
+        connections::update_around(layer, pos);
+        updates.schedule_around(pos, now + 1);
+    }
+
+    edit
+}
+
+//Buckets place their fluid, everything else places the block it is the item of.
+fn block_for(item: ItemKind) -> Option<BlockKind> {
+    match item {
+        ItemKind::WaterBucket => Some(BlockKind::Water),
+        ItemKind::LavaBucket => Some(BlockKind::Lava),
+        item => BlockKind::from_item_kind(item),
+    }
+}
+
+fn item_for(kind: BlockKind) -> ItemKind {
+    match kind {
+        BlockKind::Water => ItemKind::WaterBucket,
+        BlockKind::Lava => ItemKind::LavaBucket,
+        kind => kind.to_item_kind(),
     }
 }
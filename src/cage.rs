@@ -0,0 +1,96 @@
+use valence::{log, message::SendMessage, prelude::*};
+
+use crate::markers::{MapMarkers, Region};
+use crate::round::RoundState;
+use crate::teams::Team;
+
+pub struct CagePlugin;
+
+impl Plugin for CagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (cage_golems, keep_caged, release_golems).chain());
+    }
+}
+
+/// Where golems are put when they're caged, the middle of the floor of the `"cage"`
+/// region. Golems are held in that region while sheep get their head start.
+pub fn cage_center(cage: &Region) -> DVec3 {
+    DVec3::new(
+        (cage.min.x.floor() + cage.max.x.floor() + 1.0) / 2.0,
+        cage.min.y.floor(),
+        (cage.min.z.floor() + cage.max.z.floor() + 1.0) / 2.0,
+    )
+}
+
+//Pulls a golem back inside, keeping its body clear of the walls.
+fn clamp_to_cage(cage: &Region, pos: DVec3) -> DVec3 {
+    const HALF_WIDTH: f64 = 0.3;
+    let min = cage.min.floor() + DVec3::new(HALF_WIDTH, 0.0, HALF_WIDTH);
+    let max = cage.max.floor() + DVec3::new(1.0 - HALF_WIDTH, 0.0, 1.0 - HALF_WIDTH);
+
+    //Not DVec3::clamp, which panics on cages thinner than a golem.
+    pos.max(min).min(max)
+}
+
+//Marker for golems currently held in the cage.
+#[derive(Component, Debug)]
+struct Caged;
+
+//Also catches golems that join during the head start, like those moved by the team balancer.
+fn cage_golems(
+    mut golems: Query<(Entity, &mut Client, &Team, &mut Position), Without<Caged>>,
+    markers: Res<MapMarkers>,
+    state: Res<RoundState>,
+    mut commands: Commands,
+    mut warned: Local<bool>,
+) {
+    if *state != RoundState::SheepHeadStart {
+        return;
+    }
+
+    let Some(cage) = markers.region("cage") else {
+        if !*warned {
+            log::warn!(
+                "This map has no \"cage\" region. Golems will not be held during the head start."
+            );
+            *warned = true;
+        }
+        return;
+    };
+
+    for (entity, mut client, team, mut pos) in &mut golems {
+        if *team != Team::Golem {
+            continue;
+        }
+
+        pos.set(cage_center(cage));
+        commands.entity(entity).insert(Caged);
+        client.send_chat_message("You are caged while the sheep get a head start.");
+    }
+}
+
+fn keep_caged(mut golems: Query<&mut Position, With<Caged>>, markers: Res<MapMarkers>) {
+    let Some(cage) = markers.region("cage") else {
+        return;
+    };
+
+    for mut pos in &mut golems {
+        let inside = clamp_to_cage(cage, pos.0);
+        if inside != pos.0 {
+            pos.set(inside);
+        }
+    }
+}
+
+fn release_golems(
+    golems: Query<(Entity, Option<&Team>), With<Caged>>,
+    state: Res<RoundState>,
+    mut commands: Commands,
+) {
+    //Golems who leave their team during the head start are let out early.
+    for (golem, team) in &golems {
+        if *state != RoundState::SheepHeadStart || team != Some(&Team::Golem) {
+            commands.entity(golem).remove::<Caged>();
+        }
+    }
+}
@@ -54,6 +54,17 @@ impl ColorMap {
         Ok(next_color)
     }
 
+    pub(crate) fn free_colors(&self, team: &Team) -> usize {
+        PlayerColor::iter()
+            .filter(|col| col.valid_for_team(team))
+            .filter(|col| !self.players.contains_key(col))
+            .count()
+    }
+
+    pub(crate) fn has_room(&self, team: &Team) -> bool {
+        self.free_colors(team) > 0
+    }
+
     pub(crate) fn unregister_player(&mut self, entity: Entity) {
         match self.color_of_player(entity) {
             Some(ref color) => {
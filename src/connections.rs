@@ -0,0 +1,189 @@
+use std::iter;
+
+use valence::prelude::*;
+
+use crate::placement::{counter_clockwise, opposite, prop_val_to_dir};
+
+//How fences, walls, panes and stairs join up with the blocks around them, ported from
+//`updateShape` of each block. Only blocks changed at runtime need this, imported maps
+//already come with the right properties.
+
+const SIDES: [(Direction, PropName); 4] = [
+    (Direction::North, PropName::North),
+    (Direction::East, PropName::East),
+    (Direction::South, PropName::South),
+    (Direction::West, PropName::West),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Connector {
+    Fence { nether: bool },
+    Pane,
+    Wall,
+}
+
+impl Connector {
+    fn of(kind: BlockKind) -> Option<Self> {
+        let name = kind.to_str();
+        if name.ends_with("_fence") {
+            Some(Self::Fence {
+                nether: kind == BlockKind::NetherBrickFence,
+            })
+        } else if name.ends_with("_pane") || kind == BlockKind::IronBars {
+            Some(Self::Pane)
+        } else if name.ends_with("_wall") {
+            Some(Self::Wall)
+        } else {
+            None
+        }
+    }
+
+    //Whether this joins up with `other`, which is towards `dir`.
+    fn connects_to(self, other: BlockState, dir: Direction) -> bool {
+        let other_kind = other.to_kind();
+
+        if other_kind.to_str().ends_with("_fence_gate") {
+            //Gates only join on their sides, not their front or back.
+            let across = other
+                .get(PropName::Facing)
+                .and_then(prop_val_to_dir)
+                .is_some_and(|facing| facing == dir || facing == opposite(dir));
+            return self != Self::Pane && !across;
+        }
+
+        match (self, Self::of(other_kind)) {
+            (Self::Fence { nether }, Some(Self::Fence { nether: other })) => nether == other,
+            (Self::Pane | Self::Wall, Some(Self::Pane | Self::Wall)) => true,
+            _ => other.is_opaque(),
+        }
+    }
+}
+
+/// Recomputes the connections of `pos` and the six blocks around it. Call this after
+/// setting or removing a block.
+pub fn update_around(layer: &mut ChunkLayer, pos: BlockPos) {
+    let around = [
+        Direction::Down,
+        Direction::Up,
+        Direction::North,
+        Direction::South,
+        Direction::West,
+        Direction::East,
+    ]
+    .map(|dir| pos.get_in_direction(dir));
+
+    for pos in iter::once(pos).chain(around) {
+        let Some(state) = layer.block(pos).map(|block| block.state) else {
+            continue;
+        };
+
+        let updated = connect(layer, pos, state);
+        if updated != state {
+            layer.set_block(pos, updated);
+        }
+    }
+}
+
+fn connect(layer: &ChunkLayer, pos: BlockPos, state: BlockState) -> BlockState {
+    let block_at = |pos: BlockPos| {
+        layer
+            .block(pos)
+            .map(|block| block.state)
+            .unwrap_or(BlockState::AIR)
+    };
+
+    let kind = state.to_kind();
+    if kind.to_str().ends_with("_stairs") {
+        return state.set(PropName::Shape, stairs_shape(&block_at, pos, state));
+    }
+
+    let Some(connector) = Connector::of(kind) else {
+        return state;
+    };
+
+    let mut state = state;
+    let connected =
+        SIDES.map(|(dir, _)| connector.connects_to(block_at(pos.get_in_direction(dir)), dir));
+
+    if connector == Connector::Wall {
+        //Sides run up to full height when there's a solid block on top of the wall.
+        let above = block_at(BlockPos::new(pos.x, pos.y + 1, pos.z));
+        let height = if above.is_opaque() {
+            PropValue::Tall
+        } else {
+            PropValue::Low
+        };
+
+        for (&(_, prop), connected) in SIDES.iter().zip(connected) {
+            state = state.set(prop, if connected { height } else { PropValue::None });
+        }
+
+        //Straight walls don't need a post in the middle.
+        let [north, east, south, west] = connected;
+        let straight = (north && south && !east && !west) || (east && west && !north && !south);
+        return state.set(PropName::Up, bool_val(!straight));
+    }
+
+    for (&(_, prop), connected) in SIDES.iter().zip(connected) {
+        state = state.set(prop, bool_val(connected));
+    }
+
+    state
+}
+
+//Stairs turn into corners when another stair is against their front or back at a right angle.
+fn stairs_shape(
+    block_at: &impl Fn(BlockPos) -> BlockState,
+    pos: BlockPos,
+    state: BlockState,
+) -> PropValue {
+    let Some(facing) = state.get(PropName::Facing).and_then(prop_val_to_dir) else {
+        return PropValue::Straight;
+    };
+
+    let stairs_facing = |other: BlockState| {
+        let is_stairs = other.to_kind().to_str().ends_with("_stairs");
+        let same_half = other.get(PropName::Half) == state.get(PropName::Half);
+        (is_stairs && same_half)
+            .then(|| other.get(PropName::Facing).and_then(prop_val_to_dir))
+            .flatten()
+    };
+
+    let turns = |dir: Direction| dir != facing && dir != opposite(facing);
+
+    //A stair right next to this one that would end up facing the same way blocks the corner.
+    let can_take_shape = |dir: Direction| {
+        let other = block_at(pos.get_in_direction(dir));
+        stairs_facing(other) != Some(facing)
+    };
+
+    if let Some(back) = stairs_facing(block_at(pos.get_in_direction(facing))) {
+        if turns(back) && can_take_shape(opposite(back)) {
+            return if back == counter_clockwise(facing) {
+                PropValue::OuterLeft
+            } else {
+                PropValue::OuterRight
+            };
+        }
+    }
+
+    if let Some(front) = stairs_facing(block_at(pos.get_in_direction(opposite(facing)))) {
+        if turns(front) && can_take_shape(front) {
+            return if front == counter_clockwise(facing) {
+                PropValue::InnerLeft
+            } else {
+                PropValue::InnerRight
+            };
+        }
+    }
+
+    PropValue::Straight
+}
+
+fn bool_val(value: bool) -> PropValue {
+    if value {
+        PropValue::True
+    } else {
+        PropValue::False
+    }
+}
@@ -2,7 +2,7 @@ use std::borrow::Cow;
 
 use bevy_ecs::query::QueryData;
 use valence::app::Plugin;
-use valence::entity::entity::{CustomName, NameVisible};
+use valence::entity::entity::{CustomName, Flags, NameVisible};
 use valence::entity::iron_golem::IronGolemEntityBundle;
 use valence::entity::sheep::{Color, SheepEntityBundle};
 use valence::entity::{EntityAnimations, EntityId, EntityStatuses, OnGround, Velocity};
@@ -15,7 +15,9 @@ use valence::scoreboard::{Objective, ObjectiveBundle, ObjectiveDisplay, Objectiv
 use valence::text::color::{NamedColor, RgbColor};
 
 use crate::color::{ColorMap, PlayerColor};
-use crate::teams::{JoinTeamEvent, Team};
+use crate::economy::Gold;
+use crate::spirit::{SheepRescued, Spirit};
+use crate::teams::{JoinTeamEvent, LeaveTeamEvent, Team};
 
 /*
 TODO: I'm currently thinking that I can make a public API for this via events?
@@ -40,18 +42,39 @@ impl Plugin for DisguisePlugin {
     fn build(&self, app: &mut valence::prelude::App) {
         app.add_systems(Startup, setup).add_systems(
             Update,
-            (init_clients, spawn_clones, update_clones, update_scoreboard),
+            (
+                init_clients,
+                spawn_clones,
+                update_clones,
+                update_scoreboard,
+                remove_disguises,
+                spirit_form,
+            ),
         );
     }
 }
 
 /// The current disguise taken by a player. This is the type of entity currently shadowing the player.
 #[derive(Component, Debug, Copy, Clone, Hash, PartialEq, Eq)]
-enum Disguise {
+pub(crate) enum Disguise {
     // BabySheep,
     Sheep,
     Golem,
     // Wolf,
+    /// A tagged sheep. The clone is hidden, leaving only a glowing outline.
+    Spirit,
+}
+
+impl Disguise {
+    /// The width and height of the disguise's hitbox, for players that have one.
+    pub(crate) fn hitbox(disguise: Option<&Disguise>) -> (f64, f64) {
+        match disguise {
+            //Spirits are only hidden, the sheep is still there.
+            Some(Disguise::Sheep) | Some(Disguise::Spirit) => (0.9, 1.3),
+            Some(Disguise::Golem) => (1.4, 2.7),
+            None => (0.6, 1.8),
+        }
+    }
 }
 
 // Note: This is largely copied and adjusted from the ctf.rs example on the valence-rs repo on GitHub.
@@ -59,7 +82,7 @@ enum Disguise {
 
 //Marker to find player clones
 #[derive(Debug, Component)]
-struct ClonedEntity(Entity);
+pub(crate) struct ClonedEntity(pub(crate) Entity);
 
 //Fields that need to be mirrored by the clones to look realistic
 #[derive(Debug, QueryData)]
@@ -163,6 +186,71 @@ fn spawn_clones(
     }
 }
 
+//Undo everything spawn_clones did for a player leaving their team.
+fn remove_disguises(
+    mut query: Query<(&Username, &mut VisibleEntityLayers)>,
+    clones: Query<(Entity, &ClonedEntity)>,
+    mut objectives: Query<&mut ObjectiveScores, With<Objective>>,
+    mut events: EventReader<LeaveTeamEvent>,
+    mut commands: Commands,
+    plug_res: Res<DisguiseResource>,
+) {
+    for event in events.read() {
+        let LeaveTeamEvent {
+            entity,
+            team,
+            color,
+        } = event;
+
+        for (clone, _) in clones.iter().filter(|(_, owner)| owner.0 == *entity) {
+            commands.entity(clone).insert(Despawned);
+        }
+
+        let Ok((ign, mut layers)) = query.get_mut(*entity) else {
+            continue;
+        };
+
+        commands.entity(*entity).remove::<Disguise>();
+        layers.0.remove(&plug_res.scoreboard_layer);
+        objectives
+            .single_mut()
+            .remove(&scoreboard_key(ign, team, *color));
+    }
+}
+
+fn spirit_form(
+    spirits: Query<Entity, Added<Spirit>>,
+    mut rescued: EventReader<SheepRescued>,
+    mut clones: Query<(&ClonedEntity, &mut Flags)>,
+    on_team: Query<(), With<Team>>,
+    mut commands: Commands,
+) {
+    let mut set_form = |owner: Entity, disguise: Disguise| {
+        let Some(mut ent) = commands.get_entity(owner) else {
+            return;
+        };
+        ent.insert(disguise);
+
+        let spirit = disguise == Disguise::Spirit;
+        for (_, mut flags) in clones.iter_mut().filter(|(clone, _)| clone.0 == owner) {
+            flags.set_invisible(spirit);
+            flags.set_glowing(spirit);
+        }
+    };
+
+    for owner in &spirits {
+        set_form(owner, Disguise::Spirit);
+    }
+
+    //Only sheep can become spirits, so that's what they return to. Spirits also stop being
+    //spirits when they leave their team, but remove_disguises takes care of those.
+    for event in rescued.read() {
+        if on_team.contains(event.sheep) {
+            set_form(event.sheep, Disguise::Sheep);
+        }
+    }
+}
+
 #[derive(Debug, Resource)]
 struct DisguiseResource {
     player_team_layer: Entity,
@@ -210,35 +298,37 @@ fn init_clients(
     }
 }
 
+//Every player on a team is listed with their gold balance as the score.
 fn update_scoreboard(
-    players: Query<(&Username, &Team, &PlayerColor)>,
+    players: Query<(&Username, &Team, &PlayerColor, Option<&Gold>)>,
+    balances: Query<(), Changed<Gold>>,
     mut objectives: Query<&mut ObjectiveScores, With<Objective>>,
     colors: Res<ColorMap>,
 ) {
-    if !colors.is_changed() {
+    if !colors.is_changed() && balances.is_empty() {
         return;
     }
 
     //TODO: This might be a bad way of getting the objective?
     let mut obj = objectives.single_mut();
 
-    let mut i = 0;
-    for (ign, _team, _color) in &players {
-        obj.insert(
-            format!(
-                "{}{}",
-                match *_team {
-                    Team::Sheep => "🐏",
-                    Team::Golem => "🗡",
-                },
-                Text::to_legacy_lossy(&format_ign(ign, *_color))
-            ),
-            i,
-        );
-        i += 1;
+    for (ign, team, color, gold) in &players {
+        let gold = gold.copied().unwrap_or_default();
+        obj.insert(scoreboard_key(ign, team, *color), gold.0 as i32);
     }
 }
 
+fn scoreboard_key(ign: &Username, team: &Team, color: PlayerColor) -> String {
+    format!(
+        "{}{}",
+        match *team {
+            Team::Sheep => "🐏",
+            Team::Golem => "🗡",
+        },
+        Text::to_legacy_lossy(&format_ign(ign, color))
+    )
+}
+
 fn to_sheep_color(color: &PlayerColor) -> Color {
     //NOTE: Even though I don't intend for sheep to be able to have all 16 colors,
     //I will keep it functional in case I change my mind
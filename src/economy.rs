@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use valence::{prelude::*, uuid::Uuid};
+
+use crate::ownership::BlockOwnership;
+use crate::round::{secs_to_ticks, RoundState};
+use crate::teams::{JoinTeamEvent, LeaveTeamEvent, Team};
+
+pub struct EconomyPlugin;
+
+impl Plugin for EconomyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EconomySettings>()
+            .add_systems(Update, (grant_starting_gold, pay_farm_income, remove_gold));
+    }
+}
+
+/// A player's balance. Only players on a team have one.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Gold(pub u32);
+
+impl Gold {
+    /// Removes `amount` from the balance, or returns false if it can't be afforded.
+    pub fn try_spend(&mut self, amount: u32) -> bool {
+        match self.0.checked_sub(amount) {
+            Some(left) => {
+                self.0 = left;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct EconomySettings {
+    pub starting_gold_sheep: u32,
+    pub starting_gold_golem: u32,
+    pub income_interval_secs: u32,
+    /// Gold paid to the owner of each block of this kind every income interval.
+    pub farm_income: HashMap<BlockKind, u32>,
+    /// Gold charged to sheep for placing a block of this kind. Unlisted blocks are free.
+    pub block_costs: HashMap<BlockKind, u32>,
+}
+
+impl Default for EconomySettings {
+    fn default() -> Self {
+        Self {
+            starting_gold_sheep: 20,
+            starting_gold_golem: 0,
+            income_interval_secs: 5,
+            farm_income: HashMap::from([(BlockKind::HayBlock, 1)]),
+            block_costs: HashMap::from([
+                (BlockKind::WhiteWool, 1),
+                (BlockKind::OakPlanks, 2),
+                (BlockKind::Cobblestone, 4),
+                (BlockKind::OakFence, 2),
+                (BlockKind::OakFenceGate, 3),
+                (BlockKind::OakDoor, 3),
+                (BlockKind::HayBlock, 10),
+                (BlockKind::Sand, 2),
+                (BlockKind::Gravel, 2),
+                (BlockKind::Water, 5),
+            ]),
+        }
+    }
+}
+
+impl EconomySettings {
+    pub fn cost_of(&self, kind: BlockKind) -> u32 {
+        self.block_costs.get(&kind).copied().unwrap_or(0)
+    }
+}
+
+fn grant_starting_gold(
+    mut events: EventReader<JoinTeamEvent>,
+    settings: Res<EconomySettings>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        let gold = match event.team {
+            Team::Sheep => settings.starting_gold_sheep,
+            Team::Golem => settings.starting_gold_golem,
+        };
+
+        commands.entity(event.entity).insert(Gold(gold));
+    }
+}
+
+fn pay_farm_income(
+    mut players: Query<(&UniqueId, &mut Gold)>,
+    layers: Query<&BlockOwnership>,
+    settings: Res<EconomySettings>,
+    round: Res<RoundState>,
+    server: Res<Server>,
+) {
+    let interval = secs_to_ticks(settings.income_interval_secs, &server).max(1);
+    if !round.is_running() || server.current_tick() % interval != 0 {
+        return;
+    }
+
+    let mut income: HashMap<Uuid, u32> = HashMap::new();
+    for owners in &layers {
+        for (_, block) in owners.iter() {
+            if block.team != Some(Team::Sheep) {
+                continue;
+            }
+
+            if let Some(amount) = settings.farm_income.get(&block.kind) {
+                *income.entry(block.owner).or_default() += amount;
+            }
+        }
+    }
+
+    for (uuid, mut gold) in &mut players {
+        if let Some(amount) = income.get(&uuid.0) {
+            gold.0 += amount;
+        }
+    }
+}
+
+fn remove_gold(mut events: EventReader<LeaveTeamEvent>, mut commands: Commands) {
+    for event in events.read() {
+        if let Some(mut ent) = commands.get_entity(event.entity) {
+            ent.remove::<Gold>();
+        }
+    }
+}
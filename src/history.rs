@@ -0,0 +1,245 @@
+use std::collections::VecDeque;
+
+use valence::{
+    command::{handler::CommandResultEvent, AddCommand},
+    command_macros::Command,
+    message::SendMessage,
+    prelude::*,
+};
+
+use crate::connections;
+use crate::ownership::{BlockOwnership, PlacedBlock};
+use crate::perms::OperMode;
+use crate::physics::BlockUpdates;
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HistorySettings>()
+            .add_command::<UndoCommand>()
+            .add_command::<RedoCommand>()
+            .add_systems(
+                Update,
+                (init_clients, handle_undo_command, handle_redo_command),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct HistorySettings {
+    /// How many edits each player can undo. The oldest ones are dropped first.
+    pub max_edits: usize,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self { max_edits: 100 }
+    }
+}
+
+/// A block that was changed by an edit, along with who owned it on either side of the change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockChange {
+    pub pos: BlockPos,
+    pub before: BlockState,
+    pub after: BlockState,
+    pub placed_before: Option<PlacedBlock>,
+    pub placed_after: Option<PlacedBlock>,
+}
+
+/// The blocks a player in [`OperMode`] placed and broke, so they can be undone.
+#[derive(Component, Debug)]
+pub struct EditHistory {
+    undo: VecDeque<Vec<BlockChange>>,
+    redo: Vec<Vec<BlockChange>>,
+    max_edits: usize,
+}
+
+impl EditHistory {
+    pub fn new(max_edits: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            max_edits,
+        }
+    }
+
+    /// Adds an edit made up of every block it changed. Anything that was undone
+    /// can't be redone anymore.
+    pub fn record(&mut self, edit: Vec<BlockChange>) {
+        if edit.is_empty() || self.max_edits == 0 {
+            return;
+        }
+
+        self.redo.clear();
+        if self.undo.len() >= self.max_edits {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(edit);
+    }
+
+    fn undo(&mut self) -> Option<&[BlockChange]> {
+        let edit = self.undo.pop_back()?;
+        self.redo.push(edit);
+        self.redo.last().map(Vec::as_slice)
+    }
+
+    fn redo(&mut self) -> Option<&[BlockChange]> {
+        let edit = self.redo.pop()?;
+        self.undo.push_back(edit);
+        self.undo.back().map(Vec::as_slice)
+    }
+}
+
+#[derive(Command, Debug, Clone)]
+#[paths("undo")]
+#[scopes("danny.op")]
+struct UndoCommand;
+
+#[derive(Command, Debug, Clone)]
+#[paths("redo")]
+#[scopes("danny.op")]
+struct RedoCommand;
+
+fn init_clients(
+    clients: Query<Entity, Added<Client>>,
+    settings: Res<HistorySettings>,
+    mut commands: Commands,
+) {
+    for client in &clients {
+        commands
+            .entity(client)
+            .insert(EditHistory::new(settings.max_edits));
+    }
+}
+
+fn handle_undo_command(
+    mut events: EventReader<CommandResultEvent<UndoCommand>>,
+    mut clients: Query<(&mut Client, &mut EditHistory, Has<OperMode>)>,
+    mut layers: Query<(&mut ChunkLayer, &mut BlockOwnership, &mut BlockUpdates)>,
+    server: Res<Server>,
+) {
+    let Ok((mut layer, mut owners, mut updates)) = layers.get_single_mut() else {
+        return;
+    };
+
+    for event in events.read() {
+        let Ok((mut client, mut history, is_op)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+
+        if !is_op {
+            client.send_chat_message("You need to be in op mode to undo.");
+            continue;
+        }
+
+        let Some(edit) = history.undo() else {
+            client.send_chat_message("There is nothing to undo.");
+            continue;
+        };
+
+        //Changes are undone last to first, so blocks changed twice end up as they began.
+        let restored = edit.iter().rev().map(|change| {
+            (
+                change.pos,
+                change.after,
+                change.before,
+                change.placed_before,
+            )
+        });
+        let skipped = apply(
+            &mut layer,
+            &mut owners,
+            &mut updates,
+            restored,
+            server.current_tick(),
+        );
+
+        client.send_chat_message(report("Undid", edit.len(), skipped));
+    }
+}
+
+fn handle_redo_command(
+    mut events: EventReader<CommandResultEvent<RedoCommand>>,
+    mut clients: Query<(&mut Client, &mut EditHistory, Has<OperMode>)>,
+    mut layers: Query<(&mut ChunkLayer, &mut BlockOwnership, &mut BlockUpdates)>,
+    server: Res<Server>,
+) {
+    let Ok((mut layer, mut owners, mut updates)) = layers.get_single_mut() else {
+        return;
+    };
+
+    for event in events.read() {
+        let Ok((mut client, mut history, is_op)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+
+        if !is_op {
+            client.send_chat_message("You need to be in op mode to redo.");
+            continue;
+        }
+
+        let Some(edit) = history.redo() else {
+            client.send_chat_message("There is nothing to redo.");
+            continue;
+        };
+
+        let reapplied = edit
+            .iter()
+            .map(|change| (change.pos, change.before, change.after, change.placed_after));
+        let skipped = apply(
+            &mut layer,
+            &mut owners,
+            &mut updates,
+            reapplied,
+            server.current_tick(),
+        );
+
+        client.send_chat_message(report("Redid", edit.len(), skipped));
+    }
+}
+
+//Sets each block that is still what the edit expects it to be, and gives it back to whoever
+//owned it. Blocks changed by something else since are left alone. Returns how many were.
+fn apply(
+    layer: &mut ChunkLayer,
+    owners: &mut BlockOwnership,
+    updates: &mut BlockUpdates,
+    blocks: impl Iterator<Item = (BlockPos, BlockState, BlockState, Option<PlacedBlock>)>,
+    now: i64,
+) -> usize {
+    let mut skipped = 0;
+
+    for (pos, expected, state, placed) in blocks {
+        //Only the kind is compared, since connections and doors change the rest of the state.
+        let current = layer.block(pos).map(|block| block.state.to_kind());
+        if current != Some(expected.to_kind()) {
+            skipped += 1;
+            continue;
+        }
+
+        layer.set_block(pos, state);
+        match placed {
+            Some(placed) => owners.record(pos, placed),
+            None => {
+                owners.forget(pos);
+            }
+        }
+        connections::update_around(layer, pos);
+        updates.schedule_around(pos, now + 1);
+    }
+
+    skipped
+}
+
+fn report(verb: &str, changes: usize, skipped: usize) -> String {
+    if skipped == 0 {
+        format!("{verb} {changes} block change(s).")
+    } else {
+        format!(
+            "{verb} {} block change(s). {skipped} were skipped because the blocks changed since.",
+            changes - skipped
+        )
+    }
+}
@@ -1,16 +1,50 @@
 use anticheat::AnticheatPlugin;
+use arena::ArenaPlugin;
+use block_health::BlockHealthPlugin;
+use build_rules::BuildRulesPlugin;
 use building::BuildingPlugin;
+use cage::CagePlugin;
 use disguise::DisguisePlugin;
+use economy::EconomyPlugin;
+use history::HistoryPlugin;
+use markers::MarkersPlugin;
+use ownership::OwnershipPlugin;
 use perms::PermissionsPlugin;
+use physics::PhysicsPlugin;
+use reachability::ReachabilityPlugin;
+use reset::ResetPlugin;
+use results::ResultsPlugin;
+use round::RoundPlugin;
+use spectator::SpectatorPlugin;
+use spirit::SpiritPlugin;
+use tagging::TaggingPlugin;
 use teams::TeamPlugin;
 use valence::app::{PluginGroup, PluginGroupBuilder};
 
 pub mod anticheat;
+pub mod arena;
+pub mod block_health;
 pub mod brand;
+pub mod build_rules;
 pub mod building;
+pub mod cage;
 pub mod color;
+pub mod connections;
 pub mod disguise;
+pub mod economy;
+pub mod history;
+pub mod markers;
+pub mod ownership;
 pub mod perms;
+pub mod physics;
+pub mod placement;
+pub mod reachability;
+pub mod reset;
+pub mod results;
+pub mod round;
+pub mod spectator;
+pub mod spirit;
+pub mod tagging;
 pub mod teams;
 
 pub struct SheeptagPlugins;
@@ -23,5 +57,21 @@ impl PluginGroup for SheeptagPlugins {
             .add(AnticheatPlugin)
             .add(PermissionsPlugin)
             .add(BuildingPlugin)
+            .add(OwnershipPlugin)
+            .add(EconomyPlugin)
+            .add(BlockHealthPlugin)
+            .add(RoundPlugin)
+            .add(TaggingPlugin)
+            .add(SpiritPlugin)
+            .add(SpectatorPlugin)
+            .add(CagePlugin)
+            .add(MarkersPlugin)
+            .add(ArenaPlugin)
+            .add(BuildRulesPlugin)
+            .add(ReachabilityPlugin)
+            .add(PhysicsPlugin)
+            .add(ResetPlugin)
+            .add(HistoryPlugin)
+            .add(ResultsPlugin)
     }
 }
@@ -7,19 +7,15 @@ use valence::prelude::*;
 
 use valence::registry::RegistryIdx;
 use valence::spawn::IsFlat;
+use valence_sheeptag::arena::ArenaBounds;
 use valence_sheeptag::brand::SheeptagBrandPlugin;
+use valence_sheeptag::markers::{MapMarkers, MarkerSettings};
+use valence_sheeptag::reset::ArenaSnapshot;
 use valence_sheeptag::SheeptagPlugins;
 
 #[derive(Resource)]
 struct DanWorldFile(&'static str);
 
-#[derive(Resource)]
-struct SpawnLocation {
-    pos: [f64; 3],
-    yaw: f32,
-    pitch: f32,
-}
-
 fn main() {
     App::new()
         .insert_resource(DanWorldFile("demo_world.dan"))
@@ -37,6 +33,7 @@ fn setup(
     world_file: Res<DanWorldFile>,
     biomes: Res<BiomeRegistry>,
     dimensions: Res<DimensionTypeRegistry>,
+    marker_settings: Res<MarkerSettings>,
 ) {
     let world = match DanWorld::load(world_file.0) {
         Ok(world) => world,
@@ -68,8 +65,8 @@ fn setup(
     };
 
     let mut layer = LayerBundle::new(dim, &dimensions, &biomes, &server);
-    place_world(world, &mut layer, &mut commands);
-    commands.spawn(layer);
+    let snapshot = place_world(world, &mut layer, &mut commands, &marker_settings);
+    commands.spawn((layer, snapshot));
 }
 
 fn init_clients(
@@ -85,8 +82,10 @@ fn init_clients(
         Added<Client>,
     >,
     layers: Query<Entity, With<ChunkLayer>>,
-    spawn: Res<SpawnLocation>,
+    markers: Res<MapMarkers>,
 ) {
+    let spawn = markers.lobby_spawn();
+
     for (mut layer_id, mut visible_chunk_layer, mut pos, mut look, mut gm, mut flat) in &mut clients
     {
         let layer = layers.single();
@@ -94,15 +93,18 @@ fn init_clients(
         layer_id.0 = layer;
         visible_chunk_layer.0 = layer;
 
-        pos.set(spawn.pos);
-        look.yaw = spawn.yaw;
-        look.pitch = spawn.pitch;
+        spawn.teleport(&mut pos, &mut look);
         *gm = GameMode::Survival;
         flat.0 = true;
     }
 }
 
-fn place_world(world: DanWorld, layer: &mut LayerBundle, commands: &mut Commands) {
+fn place_world(
+    world: DanWorld,
+    layer: &mut LayerBundle,
+    commands: &mut Commands,
+    marker_settings: &MarkerSettings,
+) -> ArenaSnapshot {
     let width_and_padding = (world.width as i32) + 10;
     let depth_and_padding = (world.depth as i32) + 10;
 
@@ -158,21 +160,28 @@ fn place_world(world: DanWorld, layer: &mut LayerBundle, commands: &mut Commands
         }
     }
 
-    if let Some(spawn) = &world.get_extra("spawn") {
-        let Ok(coords) = spawn.to_coords() else {
-            commands.insert_resource(SpawnLocation {
-                pos: [0.5, 65.0, 0.0],
-                yaw: 0.0,
-                pitch: 0.0,
-            });
-            return;
-        };
-
-        let pos = [coords[0], coords[1] + BASE_Y as f64, coords[2]];
-        let yaw = coords[3] as f32;
-        let pitch = coords[4] as f32;
-        commands.insert_resource(SpawnLocation { pos, yaw, pitch });
-    }
+    let markers = MapMarkers::load(&world, BASE_Y as f64, marker_settings);
+    let bounds = ArenaBounds::from_world(&world, BASE_Y as f64, &markers);
+
+    //Taken before anyone joins, so every round starts from the map as it was made. Only
+    //the height the world actually has is kept, the arena itself may reach the sky.
+    let sections = world
+        .chunks
+        .iter()
+        .map(|chunk| chunk.sections.len())
+        .max()
+        .unwrap_or(0);
+    let mut region = bounds.0;
+    region.max.y = region
+        .max
+        .y
+        .min(BASE_Y as f64 + sections as f64 * 16.0 - 1.0);
+    let snapshot = ArenaSnapshot::capture(&layer.chunk, &region);
+
+    commands.insert_resource(bounds);
+    commands.insert_resource(markers);
+
+    snapshot
 }
 
 fn set_props(mut state: BlockState, data: &[DanBlockData]) -> BlockState {
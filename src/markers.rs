@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::iter;
+
+use dan_world::DanWorld;
+use valence::{log, prelude::*};
+
+use crate::round::{RoundEnded, RoundStarted};
+use crate::teams::Team;
+
+pub struct MarkersPlugin;
+
+impl Plugin for MarkersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MarkerSettings>()
+            .init_resource::<MapMarkers>()
+            .add_systems(Update, (send_to_team_spawns, send_to_lobby));
+    }
+}
+
+/// Used when a map has no lobby spawn at all.
+const FALLBACK_SPAWN: Marker = Marker {
+    pos: DVec3::new(0.5, 65.0, 0.0),
+    yaw: 0.0,
+    pitch: 0.0,
+};
+
+/// The map extras to read into [`MapMarkers`]. A map can define several of
+/// each by numbering them: `sheep_spawn`, `sheep_spawn_2`, `sheep_spawn_3`...
+#[derive(Resource, Debug, Clone)]
+pub struct MarkerSettings {
+    /// Extras read as a single position.
+    pub points: Vec<String>,
+    /// Extras read as a box between the `<name>_min` and `<name>_max` corners.
+    pub regions: Vec<String>,
+    /// Markers a map should have. Missing ones are logged when the map is loaded.
+    pub required: Vec<String>,
+}
+
+impl Default for MarkerSettings {
+    fn default() -> Self {
+        let names = |names: &[&str]| names.iter().map(|&name| name.to_owned()).collect();
+
+        Self {
+            points: names(&[
+                "spawn",
+                "lobby_spawn",
+                "sheep_spawn",
+                "golem_spawn",
+                "build_limit",
+            ]),
+            regions: names(&["cage", "arena", "no_build"]),
+            required: names(&["lobby_spawn", "sheep_spawn", "golem_spawn", "cage"]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Marker {
+    pub pos: DVec3,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl Marker {
+    pub fn teleport(&self, pos: &mut Position, look: &mut Look) {
+        pos.set(self.pos);
+        look.yaw = self.yaw;
+        look.pitch = self.pitch;
+    }
+}
+
+/// An axis aligned box of blocks. Both corners are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub min: DVec3,
+    pub max: DVec3,
+}
+
+impl Region {
+    pub fn new(a: DVec3, b: DVec3) -> Self {
+        Self {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    pub fn contains(&self, pos: DVec3) -> bool {
+        pos.cmpge(self.min.floor()).all() && pos.cmplt(self.max.floor() + 1.0).all()
+    }
+
+    pub fn contains_block(&self, pos: BlockPos) -> bool {
+        self.contains(DVec3::new(pos.x as f64, pos.y as f64, pos.z as f64) + 0.5)
+    }
+}
+
+/// Named positions and regions read from the map extras.
+#[derive(Resource, Debug, Default)]
+pub struct MapMarkers {
+    points: HashMap<String, Vec<Marker>>,
+    regions: HashMap<String, Vec<Region>>,
+}
+
+impl MapMarkers {
+    /// Reads every marker in `settings` from the world. `y_offset` is added to every
+    /// position, matching where the world was placed in the layer.
+    pub fn load(world: &DanWorld, y_offset: f64, settings: &MarkerSettings) -> Self {
+        let read = |key: &str| -> Option<Marker> {
+            let coords = match world.get_extra(key)?.to_coords() {
+                Ok(coords) => coords,
+                Err(e) => {
+                    log::warn!("Map marker \"{key}\" could not be read: {e:?}");
+                    return None;
+                }
+            };
+
+            Some(Marker {
+                pos: DVec3::new(coords[0], coords[1] + y_offset, coords[2]),
+                yaw: coords.get(3).copied().unwrap_or_default() as f32,
+                pitch: coords.get(4).copied().unwrap_or_default() as f32,
+            })
+        };
+
+        let mut markers = Self::default();
+
+        for name in &settings.points {
+            let found: Vec<_> = numbered(name).map_while(|key| read(&key)).collect();
+            if !found.is_empty() {
+                markers.points.insert(name.clone(), found);
+            }
+        }
+
+        for name in &settings.regions {
+            let found: Vec<_> = numbered(name)
+                .map_while(|key| {
+                    let min = read(&format!("{key}_min"))?;
+                    let max = read(&format!("{key}_max"))?;
+                    Some(Region::new(min.pos, max.pos))
+                })
+                .collect();
+            if !found.is_empty() {
+                markers.regions.insert(name.clone(), found);
+            }
+        }
+
+        for name in &settings.required {
+            if !markers.points.contains_key(name) && !markers.regions.contains_key(name) {
+                log::warn!("This map is missing the \"{name}\" marker.");
+            }
+        }
+
+        markers
+    }
+
+    pub fn points(&self, name: &str) -> &[Marker] {
+        self.points.get(name).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn point(&self, name: &str) -> Option<&Marker> {
+        self.points(name).first()
+    }
+
+    pub fn regions(&self, name: &str) -> &[Region] {
+        self.regions
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn region(&self, name: &str) -> Option<&Region> {
+        self.regions(name).first()
+    }
+
+    /// Where players wait between rounds. Maps made before team spawns use `"spawn"`.
+    pub fn lobby_spawn(&self) -> Marker {
+        self.point("lobby_spawn")
+            .or_else(|| self.point("spawn"))
+            .copied()
+            .unwrap_or(FALLBACK_SPAWN)
+    }
+
+    /// The spawns of a team, falling back to the lobby spawn if the map has none.
+    pub fn team_spawns(&self, team: Team) -> Vec<Marker> {
+        let spawns = self.points(match team {
+            Team::Sheep => "sheep_spawn",
+            Team::Golem => "golem_spawn",
+        });
+
+        if spawns.is_empty() {
+            vec![self.lobby_spawn()]
+        } else {
+            spawns.to_vec()
+        }
+    }
+}
+
+fn numbered(name: &str) -> impl Iterator<Item = String> + '_ {
+    iter::once(name.to_owned()).chain((2..).map(move |i| format!("{name}_{i}")))
+}
+
+//Players are spread across their team's spawns in turn.
+fn send_to_team_spawns(
+    mut events: EventReader<RoundStarted>,
+    mut players: Query<(&Team, &mut Position, &mut Look), With<Client>>,
+    markers: Res<MapMarkers>,
+) {
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+
+    let mut next = HashMap::new();
+    for (team, mut pos, mut look) in &mut players {
+        let spawns = markers.team_spawns(*team);
+        let i = next.entry(*team).or_insert(0usize);
+
+        spawns[*i % spawns.len()].teleport(&mut pos, &mut look);
+        *i += 1;
+    }
+}
+
+fn send_to_lobby(
+    mut events: EventReader<RoundEnded>,
+    mut players: Query<(&mut Position, &mut Look), With<Client>>,
+    markers: Res<MapMarkers>,
+) {
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+
+    let spawn = markers.lobby_spawn();
+    for (mut pos, mut look) in &mut players {
+        spawn.teleport(&mut pos, &mut look);
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use valence::{prelude::*, uuid::Uuid};
+
+use crate::teams::Team;
+
+pub struct OwnershipPlugin;
+
+impl Plugin for OwnershipPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, init_layers);
+    }
+}
+
+/// Who placed a block, and when.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlacedBlock {
+    pub owner: Uuid,
+    /// `None` for blocks placed by admins without a team.
+    pub team: Option<Team>,
+    /// The server tick the block was placed on.
+    pub placed_at: i64,
+    pub kind: BlockKind,
+}
+
+/// Tracks every player-placed block in a [`ChunkLayer`]. Blocks that are not
+/// in here are part of the map.
+#[derive(Component, Debug, Default)]
+pub struct BlockOwnership {
+    blocks: HashMap<BlockPos, PlacedBlock>,
+}
+
+impl BlockOwnership {
+    pub fn record(&mut self, pos: BlockPos, block: PlacedBlock) {
+        self.blocks.insert(pos, block);
+    }
+
+    pub fn forget(&mut self, pos: BlockPos) -> Option<PlacedBlock> {
+        self.blocks.remove(&pos)
+    }
+
+    pub fn get(&self, pos: BlockPos) -> Option<&PlacedBlock> {
+        self.blocks.get(&pos)
+    }
+
+    pub fn is_player_placed(&self, pos: BlockPos) -> bool {
+        self.blocks.contains_key(&pos)
+    }
+
+    pub fn owned_by(&self, owner: Uuid) -> impl Iterator<Item = (BlockPos, &PlacedBlock)> {
+        self.blocks
+            .iter()
+            .filter(move |(_, block)| block.owner == owner)
+            .map(|(pos, block)| (*pos, block))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BlockPos, &PlacedBlock)> {
+        self.blocks.iter().map(|(pos, block)| (*pos, block))
+    }
+
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+    }
+}
+
+fn init_layers(layers: Query<Entity, Added<ChunkLayer>>, mut commands: Commands) {
+    for layer in &layers {
+        commands.entity(layer).insert(BlockOwnership::default());
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::{BTreeMap, HashSet};
+
+use valence::{interact_item::InteractItemEvent, inventory::HeldItem, prelude::*};
+
+use crate::connections;
+use crate::ownership::BlockOwnership;
+use crate::perms::OperMode;
+use crate::teams::Team;
+
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhysicsSettings>()
+            .add_systems(Update, (init_layers, run_block_updates, fill_buckets));
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct PhysicsSettings {
+    /// Ticks between each block a falling block drops.
+    pub gravity_delay: i64,
+    /// Ticks between each block water flows.
+    pub water_delay: i64,
+    /// Ticks between each block lava flows.
+    pub lava_delay: i64,
+    /// How far water flows from its source.
+    pub water_reach: u8,
+    /// How far lava flows from its source.
+    pub lava_reach: u8,
+    /// Updates past this are put off until the next tick.
+    pub max_updates_per_tick: usize,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            gravity_delay: 2,
+            water_delay: 5,
+            lava_delay: 30,
+            water_reach: 7,
+            lava_reach: 3,
+            max_updates_per_tick: 1000,
+        }
+    }
+}
+
+/// Blocks in a [`ChunkLayer`] that need to check whether they fall or flow. Anything
+/// that changes a block at runtime should schedule the blocks around it.
+#[derive(Component, Debug, Default)]
+pub struct BlockUpdates {
+    queue: BTreeMap<i64, Vec<BlockPos>>,
+}
+
+impl BlockUpdates {
+    pub fn schedule(&mut self, pos: BlockPos, tick: i64) {
+        self.queue.entry(tick).or_default().push(pos);
+    }
+
+    /// Schedules `pos` and the six blocks around it.
+    pub fn schedule_around(&mut self, pos: BlockPos, tick: i64) {
+        self.schedule(pos, tick);
+        for dir in DIRECTIONS {
+            self.schedule(pos.get_in_direction(dir), tick);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    fn take_due(&mut self, now: i64, limit: usize) -> HashSet<BlockPos> {
+        let mut due = HashSet::new();
+
+        while due.len() < limit {
+            let Some(mut entry) = self.queue.first_entry() else {
+                break;
+            };
+            if *entry.key() > now {
+                break;
+            }
+
+            let positions = entry.get_mut();
+            let take = positions.len().min(limit - due.len());
+            due.extend(positions.drain(..take));
+
+            if positions.is_empty() {
+                entry.remove();
+            }
+        }
+
+        due
+    }
+}
+
+const DIRECTIONS: [Direction; 6] = [
+    Direction::Down,
+    Direction::Up,
+    Direction::North,
+    Direction::South,
+    Direction::West,
+    Direction::East,
+];
+
+const HORIZONTAL: [Direction; 4] = [
+    Direction::North,
+    Direction::South,
+    Direction::West,
+    Direction::East,
+];
+
+//Fluids use levels 0 for a source, 1 through 7 as they flow further away and 8 when falling.
+const LEVELS: [PropValue; 9] = [
+    PropValue::_0,
+    PropValue::_1,
+    PropValue::_2,
+    PropValue::_3,
+    PropValue::_4,
+    PropValue::_5,
+    PropValue::_6,
+    PropValue::_7,
+    PropValue::_8,
+];
+
+const FALLING: u8 = 8;
+
+fn init_layers(layers: Query<Entity, Added<ChunkLayer>>, mut commands: Commands) {
+    for layer in &layers {
+        commands.entity(layer).insert(BlockUpdates::default());
+    }
+}
+
+fn run_block_updates(
+    mut layers: Query<(&mut ChunkLayer, &mut BlockUpdates, &mut BlockOwnership)>,
+    settings: Res<PhysicsSettings>,
+    server: Res<Server>,
+) {
+    let now = server.current_tick();
+
+    for (mut layer, mut updates, mut owners) in &mut layers {
+        for pos in updates.take_due(now, settings.max_updates_per_tick) {
+            let Some(state) = layer.block(pos).map(|block| block.state) else {
+                continue;
+            };
+
+            let kind = state.to_kind();
+            if falls(kind) {
+                fall(
+                    &mut layer,
+                    &mut updates,
+                    &mut owners,
+                    &settings,
+                    pos,
+                    state,
+                    now,
+                );
+            } else if matches!(kind, BlockKind::Water | BlockKind::Lava) {
+                flow(&mut layer, &mut updates, &settings, pos, state, now);
+            }
+        }
+    }
+}
+
+fn falls(kind: BlockKind) -> bool {
+    matches!(
+        kind,
+        BlockKind::Sand
+            | BlockKind::RedSand
+            | BlockKind::Gravel
+            | BlockKind::Anvil
+            | BlockKind::ChippedAnvil
+            | BlockKind::DamagedAnvil
+    ) || kind.to_str().ends_with("_concrete_powder")
+}
+
+//Falling blocks drop one block at a time instead of becoming falling block entities.
+//Fluids they land in are washed away.
+fn fall(
+    layer: &mut ChunkLayer,
+    updates: &mut BlockUpdates,
+    owners: &mut BlockOwnership,
+    settings: &PhysicsSettings,
+    pos: BlockPos,
+    state: BlockState,
+    now: i64,
+) {
+    let below = BlockPos::new(pos.x, pos.y - 1, pos.z);
+    let Some(under) = layer.block(below).map(|block| block.state) else {
+        return;
+    };
+
+    if !under.is_air() && !matches!(under.to_kind(), BlockKind::Water | BlockKind::Lava) {
+        return;
+    }
+
+    layer.set_block(pos, BlockState::AIR);
+    layer.set_block(below, state);
+    connections::update_around(layer, pos);
+    connections::update_around(layer, below);
+
+    //Whoever placed the block still owns it after it lands.
+    if let Some(placed) = owners.forget(pos) {
+        owners.record(below, placed);
+    }
+
+    updates.schedule_around(pos, now + 1);
+    updates.schedule(below, now + settings.gravity_delay);
+}
+
+fn level_of(state: BlockState) -> u8 {
+    state
+        .get(PropName::Level)
+        .and_then(|level| LEVELS.iter().position(|&value| value == level))
+        .unwrap_or(FALLING as usize) as u8
+}
+
+fn fluid(kind: BlockKind, level: u8) -> BlockState {
+    kind.to_state()
+        .set(PropName::Level, LEVELS[level.min(FALLING) as usize])
+}
+
+//A simplified take on vanilla fluids: no infinite sources and no flowing towards holes.
+fn flow(
+    layer: &mut ChunkLayer,
+    updates: &mut BlockUpdates,
+    settings: &PhysicsSettings,
+    pos: BlockPos,
+    state: BlockState,
+    now: i64,
+) {
+    let kind = state.to_kind();
+    let (reach, delay) = match kind {
+        BlockKind::Lava => (settings.lava_reach, settings.lava_delay),
+        _ => (settings.water_reach, settings.water_delay),
+    };
+    let block_at = |layer: &ChunkLayer, pos: BlockPos| layer.block(pos).map(|block| block.state);
+    let above = BlockPos::new(pos.x, pos.y + 1, pos.z);
+    let below = BlockPos::new(pos.x, pos.y - 1, pos.z);
+
+    //Lava touching water hardens. Sources turn into obsidian, flowing lava into cobblestone.
+    if kind == BlockKind::Lava
+        && DIRECTIONS
+            .iter()
+            .filter(|&&dir| dir != Direction::Down)
+            .any(|&dir| {
+                block_at(layer, pos.get_in_direction(dir))
+                    .is_some_and(|state| state.to_kind() == BlockKind::Water)
+            })
+    {
+        let hardened = if level_of(state) == 0 {
+            BlockState::OBSIDIAN
+        } else {
+            BlockState::COBBLESTONE
+        };
+        layer.set_block(pos, hardened);
+        updates.schedule_around(pos, now + 1);
+        return;
+    }
+
+    //How far a fluid block is from a source. Falling fluid counts as a source.
+    let distance = |level: u8| if level >= FALLING { 0 } else { level };
+
+    let mut level = level_of(state);
+    if level != 0 {
+        //Flowing fluid keeps whatever level its neighbours can supply, and dries up without them.
+        let fed = if block_at(layer, above).is_some_and(|state| state.to_kind() == kind) {
+            Some(FALLING)
+        } else {
+            HORIZONTAL
+                .iter()
+                .filter_map(|&dir| block_at(layer, pos.get_in_direction(dir)))
+                .filter(|state| state.to_kind() == kind)
+                .map(|state| distance(level_of(state)) + 1)
+                .min()
+                .filter(|&level| level <= reach)
+        };
+
+        match fed {
+            Some(fed) if fed == level => {}
+            Some(fed) => {
+                level = fed;
+                layer.set_block(pos, fluid(kind, level));
+                updates.schedule_around(pos, now + delay);
+            }
+            None => {
+                layer.set_block(pos, BlockState::AIR);
+                updates.schedule_around(pos, now + delay);
+                return;
+            }
+        }
+    }
+
+    //Fluids always fall first, and only spread out once they've hit the ground.
+    match block_at(layer, below) {
+        Some(under) if under.is_air() => {
+            layer.set_block(below, fluid(kind, FALLING));
+            updates.schedule(below, now + delay);
+            return;
+        }
+        Some(under) if under.to_kind() == kind => return,
+        None => return,
+        Some(_) => {}
+    }
+
+    let spread = distance(level) + 1;
+    if spread > reach {
+        return;
+    }
+
+    for dir in HORIZONTAL {
+        let side = pos.get_in_direction(dir);
+        if block_at(layer, side).is_some_and(|state| state.is_air()) {
+            layer.set_block(side, fluid(kind, spread));
+            updates.schedule(side, now + delay);
+        }
+    }
+}
+
+//Buckets scoop up fluid sources. Players can only do that to sources placed by
+//their own team, unless they are in op mode.
+#[allow(clippy::type_complexity)]
+pub(crate) fn fill_buckets(
+    mut clients: Query<
+        (
+            &HeldItem,
+            &mut Inventory,
+            &Position,
+            &Look,
+            Option<&Team>,
+            Has<OperMode>,
+        ),
+        With<Client>,
+    >,
+    mut layers: Query<(&mut ChunkLayer, &mut BlockUpdates, &mut BlockOwnership)>,
+    mut events: EventReader<InteractItemEvent>,
+    server: Res<Server>,
+) {
+    let Ok((mut layer, mut updates, mut owners)) = layers.get_single_mut() else {
+        return;
+    };
+
+    for event in events.read() {
+        if event.hand != Hand::Main {
+            continue;
+        }
+
+        let Ok((held, mut inv, pos, look, team, is_op)) = clients.get_mut(event.client) else {
+            continue;
+        };
+
+        let stack = inv.slot(held.slot()).clone();
+        if stack.item != ItemKind::Bucket {
+            continue;
+        }
+
+        let Some((source, kind)) = find_source(&layer, pos.0, look) else {
+            continue;
+        };
+
+        //Clicking a block sends both a block and an item interaction. If the first one just
+        //emptied the bucket here, the second one shouldn't pick the water right back up.
+        if owners
+            .get(source)
+            .is_some_and(|placed| placed.placed_at == server.current_tick())
+        {
+            continue;
+        }
+
+        let owned = owners
+            .get(source)
+            .is_some_and(|placed| team.is_some() && placed.team.as_ref() == team);
+        if !is_op && !owned {
+            continue;
+        }
+
+        layer.set_block(source, BlockState::AIR);
+        owners.forget(source);
+        updates.schedule_around(source, server.current_tick() + 1);
+
+        let filled = match kind {
+            BlockKind::Lava => ItemKind::LavaBucket,
+            _ => ItemKind::WaterBucket,
+        };
+
+        if !is_op {
+            inv.set_slot(held.slot(), ItemStack::new(filled, 1, stack.nbt));
+        }
+    }
+}
+
+//Fluids can't be clicked, so the client only says it used the bucket. Walk along
+//where the player is looking until something is hit.
+fn find_source(layer: &ChunkLayer, feet: DVec3, look: &Look) -> Option<(BlockPos, BlockKind)> {
+    const REACH: f64 = 5.0;
+    const STEP: f64 = 0.1;
+
+    let eyes = feet + DVec3::new(0.0, 1.62, 0.0);
+    let (yaw, pitch) = (
+        (look.yaw as f64).to_radians(),
+        (look.pitch as f64).to_radians(),
+    );
+    let dir = DVec3::new(
+        -yaw.sin() * pitch.cos(),
+        -pitch.sin(),
+        yaw.cos() * pitch.cos(),
+    );
+
+    for i in 0..=(REACH / STEP) as i32 {
+        let point = eyes + dir * (i as f64 * STEP);
+        let pos = BlockPos::new(
+            point.x.floor() as i32,
+            point.y.floor() as i32,
+            point.z.floor() as i32,
+        );
+
+        let state = layer.block(pos)?.state;
+        let kind = state.to_kind();
+
+        if matches!(kind, BlockKind::Water | BlockKind::Lava) {
+            if level_of(state) == 0 {
+                return Some((pos, kind));
+            }
+        } else if !state.is_air() {
+            return None;
+        }
+    }
+
+    None
+}
@@ -0,0 +1,340 @@
+use valence::{math::Vec3, prelude::*};
+
+//Vanilla placement rules, mostly ported from the `getStateForPlacement` implementations
+//of each block. Blocks are told apart by their properties first, and by name when
+//several kinds of blocks share the same properties but are placed differently.
+
+/// Every block that placing `kind` against `face` of `clicked` sets, in order. The first
+/// entry is the block that was placed, any others belong to the same structure (the
+/// upper half of a door, the head of a bed...). Empty if the block can't go there.
+pub fn place(
+    layer: &ChunkLayer,
+    kind: BlockKind,
+    clicked: BlockPos,
+    face: Direction,
+    cursor: Vec3,
+    look: &Look,
+) -> Vec<(BlockPos, BlockState)> {
+    let pos = clicked.get_in_direction(face);
+    let name = kind.to_str();
+    let facing = horizontal_facing(look);
+    //Where the cursor hit, relative to the block being placed.
+    let hit = DVec3::new(
+        (clicked.x - pos.x) as f64 + cursor.x as f64,
+        (clicked.y - pos.y) as f64 + cursor.y as f64,
+        (clicked.z - pos.z) as f64 + cursor.z as f64,
+    );
+    let upper_half = match face {
+        Direction::Down => true,
+        Direction::Up => false,
+        _ => hit.y > 0.5,
+    };
+
+    if name.ends_with("_slab") {
+        //Clicking the open half of a slab with the same slab doubles it.
+        let existing = layer.block(clicked).map(|block| block.state);
+        if let Some(existing) = existing.filter(|state| state.to_kind() == kind) {
+            let merges = match existing.get(PropName::Type) {
+                Some(PropValue::Bottom) => face == Direction::Up,
+                Some(PropValue::Top) => face == Direction::Down,
+                _ => false,
+            };
+
+            if merges {
+                return vec![(clicked, existing.set(PropName::Type, PropValue::Double))];
+            }
+        }
+
+        let half = if upper_half {
+            PropValue::Top
+        } else {
+            PropValue::Bottom
+        };
+        return vec![(pos, kind.to_state().set(PropName::Type, half))];
+    }
+
+    //Hanging signs hang from ceilings, and stick out of walls when placed on a side.
+    if name.ends_with("_hanging_sign") {
+        return match (face, wall_variant(kind)) {
+            (Direction::Up, _) | (_, None) => vec![],
+            (Direction::Down, _) => vec![(pos, standing(kind, look))],
+            (_, Some(wall_kind)) => vec![(
+                pos,
+                wall_kind
+                    .to_state()
+                    .set(PropName::Facing, dir_to_prop_val(across_wall(face, look))),
+            )],
+        };
+    }
+
+    //Torches, signs, banners and heads turn into their wall variant when placed on a side.
+    if let Some(wall_kind) = wall_variant(kind) {
+        return match face {
+            Direction::Down => vec![],
+            Direction::Up => vec![(pos, standing(kind, look))],
+            _ => vec![(
+                pos,
+                wall_kind
+                    .to_state()
+                    .set(PropName::Facing, dir_to_prop_val(face)),
+            )],
+        };
+    }
+
+    let mut state = kind.to_state();
+    let props = kind.props();
+
+    if props.contains(&PropName::Axis) {
+        state = state.set(
+            PropName::Axis,
+            match face {
+                Direction::Down | Direction::Up => PropValue::Y,
+                Direction::North | Direction::South => PropValue::Z,
+                Direction::West | Direction::East => PropValue::X,
+            },
+        );
+    }
+
+    if props.contains(&PropName::Rotation) {
+        state = standing(kind, look);
+    }
+
+    if props.contains(&PropName::Face) {
+        //Buttons, levers and grindstones.
+        let (attach, facing) = match face {
+            Direction::Up => (PropValue::Floor, facing),
+            Direction::Down => (PropValue::Ceiling, facing),
+            _ => (PropValue::Wall, face),
+        };
+        state = state
+            .set(PropName::Face, attach)
+            .set(PropName::Facing, dir_to_prop_val(facing));
+    } else if props.contains(&PropName::Facing) {
+        let dir = if name.ends_with("_stairs")
+            || name.ends_with("_door")
+            || name.ends_with("_fence_gate")
+            || name.ends_with("_bed")
+        {
+            facing
+        } else if name.ends_with("_trapdoor") {
+            match face {
+                Direction::Up | Direction::Down => opposite(facing),
+                _ => face,
+            }
+        } else if name == "ladder" || name.ends_with("end_rod") || name.ends_with("lightning_rod") {
+            face
+        } else if name == "hopper" {
+            match face {
+                Direction::Up | Direction::Down => Direction::Down,
+                _ => opposite(face),
+            }
+        } else if name == "observer" {
+            looking_at(look)
+        } else if supports_vertical(kind) {
+            opposite(looking_at(look))
+        } else {
+            //Chests, furnaces and most other blocks face the player.
+            opposite(facing)
+        };
+
+        state = state.set(PropName::Facing, dir_to_prop_val(dir));
+    }
+
+    if props.contains(&PropName::Half) {
+        //Stairs and trapdoors use top/bottom, doors and tall plants use upper/lower.
+        //Setting a value a block doesn't have leaves it unchanged, so both are set.
+        let half = if upper_half {
+            PropValue::Top
+        } else {
+            PropValue::Bottom
+        };
+        state = state
+            .set(PropName::Half, half)
+            .set(PropName::Half, PropValue::Lower);
+    }
+
+    if props.contains(&PropName::Hinge) {
+        state = state.set(PropName::Hinge, door_hinge(layer, pos, facing, hit));
+    }
+
+    let mut blocks = vec![(pos, state)];
+
+    if state.get(PropName::Half) == Some(PropValue::Lower) {
+        let above = BlockPos::new(pos.x, pos.y + 1, pos.z);
+        blocks.push((above, state.set(PropName::Half, PropValue::Upper)));
+    }
+
+    if props.contains(&PropName::Part) {
+        let head = pos.get_in_direction(facing);
+        blocks.push((head, state.set(PropName::Part, PropValue::Head)));
+        blocks[0].1 = state.set(PropName::Part, PropValue::Foot);
+    }
+
+    blocks
+}
+
+fn standing(kind: BlockKind, look: &Look) -> BlockState {
+    const ROTATIONS: [PropValue; 16] = [
+        PropValue::_0,
+        PropValue::_1,
+        PropValue::_2,
+        PropValue::_3,
+        PropValue::_4,
+        PropValue::_5,
+        PropValue::_6,
+        PropValue::_7,
+        PropValue::_8,
+        PropValue::_9,
+        PropValue::_10,
+        PropValue::_11,
+        PropValue::_12,
+        PropValue::_13,
+        PropValue::_14,
+        PropValue::_15,
+    ];
+
+    let rotation = ((180.0 + look.yaw as f64) * 16.0 / 360.0 + 0.5).floor() as i32 & 15;
+    kind.to_state()
+        .set(PropName::Rotation, ROTATIONS[rotation as usize])
+}
+
+fn wall_variant(kind: BlockKind) -> Option<BlockKind> {
+    let name = kind.to_str();
+    let wall_name = if name.ends_with("torch") {
+        name.replace("torch", "wall_torch")
+    } else if name.ends_with("_hanging_sign") && !name.contains("_wall_") {
+        name.replace("_hanging_sign", "_wall_hanging_sign")
+    } else if ["_sign", "_banner", "_head", "_skull"]
+        .iter()
+        .any(|suffix| name.ends_with(suffix) && !name.contains("_wall_"))
+    {
+        let (prefix, suffix) = name.rsplit_once('_')?;
+        format!("{prefix}_wall_{suffix}")
+    } else {
+        return None;
+    };
+
+    BlockKind::from_str(&wall_name)
+}
+
+//Wall hanging signs are at a right angle to the wall, facing back towards the side
+//the player is looking from.
+fn across_wall(face: Direction, look: &Look) -> Direction {
+    let yaw = (look.yaw as f64).to_radians();
+    let (x, z) = (-yaw.sin(), yaw.cos());
+    let towards = match face {
+        Direction::East | Direction::West if z >= 0.0 => Direction::South,
+        Direction::East | Direction::West => Direction::North,
+        _ if x >= 0.0 => Direction::East,
+        _ => Direction::West,
+    };
+
+    opposite(towards)
+}
+
+//Vanilla puts the hinge on the side of a neighbouring door so the two make a
+//double door, and otherwise on whichever side of the door was clicked.
+fn door_hinge(layer: &ChunkLayer, pos: BlockPos, facing: Direction, hit: DVec3) -> PropValue {
+    let is_door_facing = |side: Direction| {
+        layer
+            .block(pos.get_in_direction(side))
+            .map(|block| block.state)
+            .is_some_and(|state| {
+                state.to_kind().to_str().ends_with("_door")
+                    && state.get(PropName::Facing) == Some(dir_to_prop_val(facing))
+            })
+    };
+
+    let left = counter_clockwise(facing);
+    if is_door_facing(left) {
+        return PropValue::Right;
+    }
+    if is_door_facing(opposite(left)) {
+        return PropValue::Left;
+    }
+
+    let right = match facing {
+        Direction::North => hit.x < 0.5,
+        Direction::South => hit.x > 0.5,
+        Direction::West => hit.z > 0.5,
+        _ => hit.z < 0.5,
+    };
+
+    if right {
+        PropValue::Right
+    } else {
+        PropValue::Left
+    }
+}
+
+fn supports_vertical(kind: BlockKind) -> bool {
+    kind.to_state()
+        .set(PropName::Facing, PropValue::Up)
+        .get(PropName::Facing)
+        == Some(PropValue::Up)
+}
+
+/// The direction the player is facing, ignoring pitch.
+pub fn horizontal_facing(look: &Look) -> Direction {
+    match ((look.yaw / 90.0).round() as i32).rem_euclid(4) {
+        0 => Direction::South,
+        1 => Direction::West,
+        2 => Direction::North,
+        _ => Direction::East,
+    }
+}
+
+/// The direction the player is looking in the most.
+pub fn looking_at(look: &Look) -> Direction {
+    if look.pitch < -45.0 {
+        Direction::Up
+    } else if look.pitch > 45.0 {
+        Direction::Down
+    } else {
+        horizontal_facing(look)
+    }
+}
+
+pub fn opposite(dir: Direction) -> Direction {
+    match dir {
+        Direction::Down => Direction::Up,
+        Direction::Up => Direction::Down,
+        Direction::North => Direction::South,
+        Direction::South => Direction::North,
+        Direction::West => Direction::East,
+        Direction::East => Direction::West,
+    }
+}
+
+pub fn counter_clockwise(dir: Direction) -> Direction {
+    match dir {
+        Direction::North => Direction::West,
+        Direction::West => Direction::South,
+        Direction::South => Direction::East,
+        Direction::East => Direction::North,
+        vertical => vertical,
+    }
+}
+
+pub fn dir_to_prop_val(dir: Direction) -> PropValue {
+    match dir {
+        Direction::Down => PropValue::Down,
+        Direction::Up => PropValue::Up,
+        Direction::North => PropValue::North,
+        Direction::South => PropValue::South,
+        Direction::West => PropValue::West,
+        Direction::East => PropValue::East,
+    }
+}
+
+pub fn prop_val_to_dir(val: PropValue) -> Option<Direction> {
+    match val {
+        PropValue::Down => Some(Direction::Down),
+        PropValue::Up => Some(Direction::Up),
+        PropValue::North => Some(Direction::North),
+        PropValue::South => Some(Direction::South),
+        PropValue::West => Some(Direction::West),
+        PropValue::East => Some(Direction::East),
+        _ => None,
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use valence::prelude::*;
+
+use crate::markers::Region;
+
+pub struct ReachabilityPlugin;
+
+impl Plugin for ReachabilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReachabilitySettings>();
+    }
+}
+
+/// Sheep may not build walls that golems can't get past. Every solid block a sheep
+/// places is checked with a walk from the golem spawns.
+#[derive(Resource, Debug, Clone)]
+pub struct ReachabilitySettings {
+    pub enabled: bool,
+    /// How many blocks a golem may stand on in a single search. Searches that hit
+    /// this limit let the placement through rather than stall the tick.
+    pub search_limit: usize,
+    /// How many open blocks stacked on top of each other a golem needs to pass.
+    pub clearance: i32,
+}
+
+impl Default for ReachabilitySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            search_limit: 50_000,
+            clearance: 3,
+        }
+    }
+}
+
+const HORIZONTAL: [Direction; 4] = [
+    Direction::North,
+    Direction::South,
+    Direction::West,
+    Direction::East,
+];
+
+/// Whether placing solid blocks at `placed` would cut any of `targets` off from
+/// every one of `starts`. Targets that were already out of reach don't count.
+pub(crate) fn seals_off(
+    layer: &ChunkLayer,
+    settings: &ReachabilitySettings,
+    bounds: Option<&Region>,
+    starts: &[BlockPos],
+    targets: &[BlockPos],
+    placed: &[BlockPos],
+) -> bool {
+    let block_at = |pos: BlockPos| layer.block(pos).map(|block| block.state);
+    seals_off_in(&block_at, settings, bounds, starts, targets, placed)
+}
+
+fn seals_off_in(
+    block_at: &impl Fn(BlockPos) -> Option<BlockState>,
+    settings: &ReachabilitySettings,
+    bounds: Option<&Region>,
+    starts: &[BlockPos],
+    targets: &[BlockPos],
+    placed: &[BlockPos],
+) -> bool {
+    if !settings.enabled || starts.is_empty() || targets.is_empty() {
+        return false;
+    }
+
+    let Some(after) = reached(block_at, settings, bounds, starts, targets, placed) else {
+        return false;
+    };
+    if after.len() == targets.len() {
+        return false;
+    }
+
+    let Some(before) = reached(block_at, settings, bounds, starts, targets, &[]) else {
+        return false;
+    };
+
+    before.difference(&after).next().is_some()
+}
+
+//Breadth first search over every block a golem can stand in. Golems can't jump, so they
+//only walk, step up half a block onto slabs and fall. Returns the indices of the targets
+//they got close enough to hit, or `None` if the search limit was hit first.
+fn reached(
+    block_at: &impl Fn(BlockPos) -> Option<BlockState>,
+    settings: &ReachabilitySettings,
+    bounds: Option<&Region>,
+    starts: &[BlockPos],
+    targets: &[BlockPos],
+    placed: &[BlockPos],
+) -> Option<HashSet<usize>> {
+    let up = |pos: BlockPos, dy: i32| BlockPos::new(pos.x, pos.y + dy, pos.z);
+
+    //Unloaded blocks are as good as solid.
+    let solid = |pos: BlockPos| {
+        placed.contains(&pos)
+            || match block_at(pos) {
+                Some(state) => state.blocks_motion(),
+                None => true,
+            }
+    };
+    let inside = |pos: BlockPos| match bounds {
+        Some(bounds) => bounds.contains_block(pos),
+        None => true,
+    };
+    let open = |pos: BlockPos| inside(pos) && (0..settings.clearance).all(|dy| !solid(up(pos, dy)));
+    let half_step = |pos: BlockPos| {
+        !placed.contains(&pos)
+            && block_at(pos)
+                .is_some_and(|state| state.get(PropName::Type) == Some(PropValue::Bottom))
+    };
+    //Where a golem in `pos` ends up after falling.
+    let settle = |mut pos: BlockPos| {
+        while open(pos) {
+            if solid(up(pos, -1)) {
+                return Some(pos);
+            }
+            pos = up(pos, -1);
+        }
+        None
+    };
+
+    //Golems can hit whatever is right next to them, from their feet to just above their head.
+    let mut approaches: HashMap<BlockPos, Vec<usize>> = HashMap::new();
+    for (i, target) in targets.iter().enumerate() {
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                for dy in -settings.clearance..=1 {
+                    let pos = BlockPos::new(target.x + dx, target.y + dy, target.z + dz);
+                    approaches.entry(pos).or_default().push(i);
+                }
+            }
+        }
+    }
+
+    let mut reached = HashSet::new();
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+
+    for &start in starts {
+        if let Some(cell) = settle(start) {
+            if visited.insert(cell) {
+                queue.push_back(cell);
+            }
+        }
+    }
+
+    while let Some(cell) = queue.pop_front() {
+        if let Some(found) = approaches.get(&cell) {
+            reached.extend(found.iter().copied());
+            if reached.len() == targets.len() {
+                break;
+            }
+        }
+
+        for dir in HORIZONTAL {
+            let side = cell.get_in_direction(dir);
+            let next = if open(side) {
+                settle(side)
+            } else if half_step(side) && open(up(side, 1)) {
+                Some(up(side, 1))
+            } else {
+                None
+            };
+
+            let Some(next) = next else {
+                continue;
+            };
+
+            if visited.len() >= settings.search_limit {
+                return None;
+            }
+
+            if visited.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    Some(reached)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //A stone floor at y = 0 with room to walk on top of it.
+    fn floor(bounds: &Region) -> HashMap<BlockPos, BlockState> {
+        let mut blocks = HashMap::new();
+        for x in bounds.min.x as i32..=bounds.max.x as i32 {
+            for z in bounds.min.z as i32..=bounds.max.z as i32 {
+                blocks.insert(BlockPos::new(x, 0, z), BlockState::STONE);
+            }
+        }
+        blocks
+    }
+
+    fn ring(center: BlockPos) -> Vec<BlockPos> {
+        let mut ring = vec![];
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                if dx != 0 || dz != 0 {
+                    ring.push(BlockPos::new(center.x + dx, center.y, center.z + dz));
+                }
+            }
+        }
+        ring
+    }
+
+    fn seals(
+        blocks: &HashMap<BlockPos, BlockState>,
+        bounds: &Region,
+        targets: &[BlockPos],
+        placed: &[BlockPos],
+    ) -> bool {
+        let block_at = |pos: BlockPos| Some(blocks.get(&pos).copied().unwrap_or(BlockState::AIR));
+        seals_off_in(
+            &block_at,
+            &ReachabilitySettings::default(),
+            Some(bounds),
+            &[BlockPos::new(0, 1, 0)],
+            targets,
+            placed,
+        )
+    }
+
+    fn arena() -> Region {
+        Region::new(DVec3::new(-10.0, 0.0, -10.0), DVec3::new(10.0, 10.0, 10.0))
+    }
+
+    #[test]
+    fn open_field_is_not_sealed() {
+        let bounds = arena();
+        let blocks = floor(&bounds);
+        let sheep = BlockPos::new(6, 1, 0);
+
+        assert!(!seals(
+            &blocks,
+            &bounds,
+            &[sheep],
+            &[BlockPos::new(3, 1, 3)]
+        ));
+    }
+
+    #[test]
+    fn golems_cannot_climb_a_low_wall() {
+        let bounds = arena();
+        let blocks = floor(&bounds);
+        let sheep = BlockPos::new(6, 1, 0);
+
+        assert!(seals(&blocks, &bounds, &[sheep], &ring(sheep)));
+    }
+
+    #[test]
+    fn unreachable_targets_do_not_allow_more_walls() {
+        let bounds = arena();
+        let mut blocks = floor(&bounds);
+        let walled_in = BlockPos::new(-6, 1, 0);
+        for pos in ring(walled_in) {
+            blocks.insert(pos, BlockState::STONE);
+        }
+        let sheep = BlockPos::new(6, 1, 0);
+
+        assert!(seals(&blocks, &bounds, &[walled_in, sheep], &ring(sheep)));
+        assert!(!seals(
+            &blocks,
+            &bounds,
+            &[walled_in, sheep],
+            &[BlockPos::new(3, 1, 3)]
+        ));
+    }
+
+    #[test]
+    fn golems_step_up_slabs() {
+        let bounds = Region::new(DVec3::new(-1.0, 0.0, 0.0), DVec3::new(8.0, 10.0, 0.0));
+        let mut blocks = floor(&bounds);
+        blocks.insert(
+            BlockPos::new(3, 1, 0),
+            BlockKind::OakSlab
+                .to_state()
+                .set(PropName::Type, PropValue::Bottom),
+        );
+        for x in 4..=8 {
+            blocks.insert(BlockPos::new(x, 1, 0), BlockState::STONE);
+        }
+        let sheep = BlockPos::new(7, 2, 0);
+
+        assert!(!seals(
+            &blocks,
+            &bounds,
+            &[sheep],
+            &[BlockPos::new(0, 5, 0)]
+        ));
+        assert!(seals(&blocks, &bounds, &[sheep], &[BlockPos::new(3, 2, 0)]));
+    }
+
+    #[test]
+    fn golems_drop_down_ledges() {
+        let bounds = Region::new(DVec3::new(-1.0, -5.0, 0.0), DVec3::new(8.0, 10.0, 0.0));
+        let mut blocks = floor(&bounds);
+        for x in 3..=8 {
+            blocks.remove(&BlockPos::new(x, 0, 0));
+            blocks.insert(BlockPos::new(x, -4, 0), BlockState::STONE);
+        }
+        let sheep = BlockPos::new(7, -3, 0);
+
+        assert!(!seals(
+            &blocks,
+            &bounds,
+            &[sheep],
+            &[BlockPos::new(0, 5, 0)]
+        ));
+    }
+}
@@ -0,0 +1,171 @@
+use valence::{log, prelude::*};
+
+use crate::block_health::BlockDamage;
+use crate::connections;
+use crate::markers::Region;
+use crate::ownership::BlockOwnership;
+use crate::physics::BlockUpdates;
+use crate::round::RoundEnded;
+
+pub struct ResetPlugin;
+
+impl Plugin for ResetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ResetSettings>()
+            .add_systems(Update, (start_reset, restore_arena).chain());
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct ResetSettings {
+    /// How many blocks are checked against the snapshot each tick. Only the ones that
+    /// changed are set.
+    pub blocks_per_tick: usize,
+}
+
+impl Default for ResetSettings {
+    fn default() -> Self {
+        Self {
+            blocks_per_tick: 65_536,
+        }
+    }
+}
+
+/// The blocks of the arena as they were when the map was loaded. Layers with this
+/// component are put back the way they were after every round.
+#[derive(Component, Debug)]
+pub struct ArenaSnapshot {
+    min: BlockPos,
+    size: [i32; 3],
+    blocks: Vec<BlockState>,
+}
+
+impl ArenaSnapshot {
+    /// Copies every block of `layer` inside `region`. The region is cut down to the
+    /// height of the layer.
+    pub fn capture(layer: &ChunkLayer, region: &Region) -> Self {
+        let min_y = layer.min_y();
+        let max_y = min_y + layer.height() as i32 - 1;
+
+        let min = BlockPos::new(
+            region.min.x.floor() as i32,
+            (region.min.y.floor() as i32).clamp(min_y, max_y),
+            region.min.z.floor() as i32,
+        );
+        let max = BlockPos::new(
+            region.max.x.floor() as i32,
+            (region.max.y.floor() as i32).clamp(min_y, max_y),
+            region.max.z.floor() as i32,
+        );
+        let size = [max.x - min.x + 1, max.y - min.y + 1, max.z - min.z + 1].map(|n| n.max(0));
+
+        let mut snapshot = Self {
+            min,
+            size,
+            blocks: Vec::with_capacity(size.iter().product::<i32>() as usize),
+        };
+
+        for i in 0..size.iter().product::<i32>() as usize {
+            let state = layer
+                .block(snapshot.pos_of(i))
+                .map(|block| block.state)
+                .unwrap_or(BlockState::AIR);
+            snapshot.blocks.push(state);
+        }
+
+        snapshot
+    }
+
+    fn contains(&self, pos: BlockPos) -> bool {
+        let offset = [pos.x - self.min.x, pos.y - self.min.y, pos.z - self.min.z];
+        offset
+            .into_iter()
+            .zip(self.size)
+            .all(|(offset, size)| (0..size).contains(&offset))
+    }
+
+    fn pos_of(&self, i: usize) -> BlockPos {
+        let [size_x, _, size_z] = self.size;
+        let i = i as i32;
+
+        BlockPos::new(
+            self.min.x + i % size_x,
+            self.min.y + i / (size_x * size_z),
+            self.min.z + i / size_x % size_z,
+        )
+    }
+}
+
+/// Added to a layer while it is being put back to its [`ArenaSnapshot`].
+#[derive(Component, Debug)]
+pub struct Restoring {
+    next: usize,
+    changed: usize,
+}
+
+//Player blocks are forgotten right away. The ones the snapshot doesn't cover are removed
+//now, the rest is put back over the next few ticks.
+fn start_reset(
+    mut layers: Query<(
+        Entity,
+        &mut ChunkLayer,
+        &ArenaSnapshot,
+        &mut BlockOwnership,
+        &mut BlockDamage,
+        &mut BlockUpdates,
+    )>,
+    mut events: EventReader<RoundEnded>,
+    mut commands: Commands,
+) {
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+
+    for (entity, mut layer, snapshot, mut owners, mut damage, mut updates) in &mut layers {
+        let outside: Vec<_> = owners
+            .iter()
+            .map(|(pos, _)| pos)
+            .filter(|&pos| !snapshot.contains(pos))
+            .collect();
+        for pos in outside {
+            layer.set_block(pos, BlockState::AIR);
+            connections::update_around(&mut layer, pos);
+        }
+
+        owners.clear();
+        damage.clear_all(&mut layer);
+        updates.clear();
+        commands.entity(entity).insert(Restoring {
+            next: 0,
+            changed: 0,
+        });
+    }
+}
+
+fn restore_arena(
+    mut layers: Query<(Entity, &mut ChunkLayer, &ArenaSnapshot, &mut Restoring)>,
+    settings: Res<ResetSettings>,
+    mut commands: Commands,
+) {
+    for (entity, mut layer, snapshot, mut restoring) in &mut layers {
+        let end = (restoring.next + settings.blocks_per_tick).min(snapshot.blocks.len());
+
+        for i in restoring.next..end {
+            let pos = snapshot.pos_of(i);
+            let state = snapshot.blocks[i];
+
+            if layer.block(pos).is_some_and(|block| block.state != state) {
+                layer.set_block(pos, state);
+                restoring.changed += 1;
+            }
+        }
+
+        restoring.next = end;
+
+        if end == snapshot.blocks.len() {
+            log::info!("Arena reset, {} blocks restored.", restoring.changed);
+            commands.entity(entity).remove::<Restoring>();
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use valence::{message::SendMessage, prelude::*};
+
+use crate::color::PlayerColor;
+use crate::round::{
+    secs_to_ticks, EndRound, RoundClock, RoundEnded, RoundSettings, RoundStarted, RoundState,
+};
+use crate::tagging::{SheepTagged, Tagged};
+use crate::teams::{LeaveTeamEvent, Team};
+
+pub struct ResultsPlugin;
+
+impl Plugin for ResultsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RoundLog>().add_systems(
+            Update,
+            (record_round, evaluate_win, announce_results).chain(),
+        );
+    }
+}
+
+/// Everything that happened during the current (or last) round, kept for the results summary.
+#[derive(Resource, Debug, Default)]
+pub struct RoundLog {
+    started_at: i64,
+    //Names are stored rather than entities, since players may disconnect before the round ends.
+    tags: Vec<(String, String)>,
+}
+
+fn record_round(
+    mut log: ResMut<RoundLog>,
+    mut started: EventReader<RoundStarted>,
+    mut tags: EventReader<SheepTagged>,
+    names: Query<&Username>,
+    server: Res<Server>,
+) {
+    if !started.is_empty() {
+        started.clear();
+        log.tags.clear();
+        log.started_at = server.current_tick();
+    }
+
+    for SheepTagged { golem, sheep } in tags.read() {
+        let (Ok(golem_ign), Ok(sheep_ign)) = (names.get(*golem), names.get(*sheep)) else {
+            continue;
+        };
+
+        log.tags.push((golem_ign.0.clone(), sheep_ign.0.clone()));
+    }
+}
+
+//Golems win once every sheep has been tagged, sheep win by outlasting the survival timer.
+fn evaluate_win(
+    sheep: Query<(&Team, Has<Tagged>), With<Client>>,
+    state: Res<RoundState>,
+    clock: Res<RoundClock>,
+    settings: Res<RoundSettings>,
+    server: Res<Server>,
+    mut ew: EventWriter<EndRound>,
+) {
+    if *state != RoundState::Hunt {
+        return;
+    }
+
+    let mut sheep = sheep.iter().filter(|&(&team, _)| team == Team::Sheep);
+    if sheep.all(|(_, tagged)| tagged) {
+        ew.send(EndRound {
+            winner: Some(Team::Golem),
+        });
+    } else if clock.elapsed(&server) >= secs_to_ticks(settings.survival_secs, &server) {
+        ew.send(EndRound {
+            winner: Some(Team::Sheep),
+        });
+    }
+}
+
+fn announce_results(
+    mut events: EventReader<RoundEnded>,
+    mut clients: Query<&mut Client>,
+    players: Query<(Entity, &Team, &PlayerColor)>,
+    log: Res<RoundLog>,
+    server: Res<Server>,
+    mut leave: EventWriter<LeaveTeamEvent>,
+) {
+    for RoundEnded { winner } in events.read() {
+        let secs = (server.current_tick() - log.started_at) / server.tick_rate().get() as i64;
+
+        let mut summary = vec![match winner {
+            Some(Team::Sheep) => "The sheep survived!".to_owned(),
+            Some(Team::Golem) => "The golems tagged every sheep!".to_owned(),
+            None => "The round ended without a winner.".to_owned(),
+        }];
+        summary.push(format!("Round time: {}m {}s", secs / 60, secs % 60));
+        summary.extend(
+            log.tags
+                .iter()
+                .map(|(golem, sheep)| format!("  {golem} tagged {sheep}")),
+        );
+
+        for mut client in &mut clients {
+            for line in &summary {
+                client.send_chat_message(line.as_str());
+            }
+        }
+
+        //Everyone goes back to the lobby without a team.
+        for (entity, &team, &color) in &players {
+            leave.send(LeaveTeamEvent {
+                entity,
+                team,
+                color,
+            });
+        }
+    }
+}
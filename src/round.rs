@@ -0,0 +1,222 @@
+use valence::{log, message::SendMessage, prelude::*, title::SetTitle};
+
+use crate::reset::Restoring;
+use crate::teams::Team;
+
+pub struct RoundPlugin;
+
+impl Plugin for RoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RoundState::Lobby)
+            .init_resource::<RoundClock>()
+            .init_resource::<RoundSettings>()
+            .add_event::<RoundStateChanged>()
+            .add_event::<RoundStarted>()
+            .add_event::<HuntStarted>()
+            .add_event::<RoundEnded>()
+            .add_event::<EndRound>()
+            .add_systems(Update, (tick_round, announce_countdown).chain());
+    }
+}
+
+/// The phase the current Sheep Tag match is in.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RoundState {
+    /// Waiting for enough players to join both teams.
+    Lobby,
+    /// Enough players are present; the round starts when the countdown runs out.
+    Countdown,
+    /// The round has started, but only sheep are free to move and build.
+    SheepHeadStart,
+    /// Golems are released and hunting sheep.
+    Hunt,
+    /// The round is over. The server returns to the lobby shortly after.
+    Ended,
+}
+
+impl RoundState {
+    /// Whether a match is currently being played.
+    pub fn is_running(&self) -> bool {
+        matches!(self, RoundState::SheepHeadStart | RoundState::Hunt)
+    }
+}
+
+/// Tracks when the current [`RoundState`] was entered.
+#[derive(Resource, Debug, Default)]
+pub struct RoundClock {
+    entered_at: i64,
+}
+
+impl RoundClock {
+    /// The number of ticks spent in the current state.
+    pub fn elapsed(&self, server: &Server) -> i64 {
+        server.current_tick() - self.entered_at
+    }
+}
+
+/// Durations are in seconds and converted to ticks using the server tick rate.
+#[derive(Resource, Debug, Clone)]
+pub struct RoundSettings {
+    pub min_sheep: usize,
+    pub min_golems: usize,
+    pub countdown_secs: u32,
+    pub head_start_secs: u32,
+    /// How long sheep have to survive the hunt to win.
+    pub survival_secs: u32,
+    pub ended_secs: u32,
+}
+
+impl Default for RoundSettings {
+    fn default() -> Self {
+        Self {
+            min_sheep: 1,
+            min_golems: 1,
+            countdown_secs: 10,
+            head_start_secs: 30,
+            survival_secs: 300,
+            ended_secs: 10,
+        }
+    }
+}
+
+pub(crate) fn secs_to_ticks(secs: u32, server: &Server) -> i64 {
+    secs as i64 * server.tick_rate().get() as i64
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RoundStateChanged {
+    pub from: RoundState,
+    pub to: RoundState,
+}
+
+/// Sent when the countdown finishes and sheep are given their head start.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RoundStarted;
+
+/// Sent when the sheep head start is over and golems are released.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct HuntStarted;
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RoundEnded {
+    /// `None` if the round was abandoned without a winner.
+    pub winner: Option<Team>,
+}
+
+/// Send this to end a running round. Ignored if no round is running.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct EndRound {
+    pub winner: Option<Team>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn tick_round(
+    mut state: ResMut<RoundState>,
+    mut clock: ResMut<RoundClock>,
+    settings: Res<RoundSettings>,
+    server: Res<Server>,
+    players: Query<&Team, With<Client>>,
+    restoring: Query<(), With<Restoring>>,
+    mut changed: EventWriter<RoundStateChanged>,
+    mut started: EventWriter<RoundStarted>,
+    mut hunt: EventWriter<HuntStarted>,
+    mut ended: EventWriter<RoundEnded>,
+    mut requests: EventReader<EndRound>,
+) {
+    let requested_winner = requests.read().last().map(|req| req.winner);
+    let sheep = players.iter().filter(|&&team| team == Team::Sheep).count();
+    let golems = players.iter().filter(|&&team| team == Team::Golem).count();
+    let enough_players = sheep >= settings.min_sheep && golems >= settings.min_golems;
+    let elapsed = clock.elapsed(&server);
+
+    let next = match *state {
+        //The next round waits for the arena to be put back together.
+        RoundState::Lobby if enough_players && restoring.is_empty() => RoundState::Countdown,
+        RoundState::Countdown if !enough_players => RoundState::Lobby,
+        RoundState::Countdown if elapsed >= secs_to_ticks(settings.countdown_secs, &server) => {
+            started.send(RoundStarted);
+            RoundState::SheepHeadStart
+        }
+        RoundState::SheepHeadStart | RoundState::Hunt if requested_winner.is_some() => {
+            ended.send(RoundEnded {
+                winner: requested_winner.flatten(),
+            });
+            RoundState::Ended
+        }
+        RoundState::SheepHeadStart | RoundState::Hunt if sheep == 0 || golems == 0 => {
+            //Everyone on one side left. Whoever is still around wins by default.
+            ended.send(RoundEnded {
+                winner: match (sheep, golems) {
+                    (0, 0) => None,
+                    (0, _) => Some(Team::Golem),
+                    _ => Some(Team::Sheep),
+                },
+            });
+            RoundState::Ended
+        }
+        RoundState::SheepHeadStart
+            if elapsed >= secs_to_ticks(settings.head_start_secs, &server) =>
+        {
+            hunt.send(HuntStarted);
+            RoundState::Hunt
+        }
+        RoundState::Ended if elapsed >= secs_to_ticks(settings.ended_secs, &server) => {
+            RoundState::Lobby
+        }
+        _ => return,
+    };
+
+    log::info!("Round state: {:?} -> {next:?}", *state);
+    changed.send(RoundStateChanged {
+        from: *state,
+        to: next,
+    });
+    *state = next;
+    clock.entered_at = server.current_tick();
+}
+
+fn announce_countdown(
+    mut clients: Query<&mut Client>,
+    state: Res<RoundState>,
+    clock: Res<RoundClock>,
+    settings: Res<RoundSettings>,
+    server: Res<Server>,
+    mut changes: EventReader<RoundStateChanged>,
+) {
+    for change in changes.read() {
+        let msg = match change.to {
+            RoundState::Lobby => "Waiting for players...",
+            RoundState::Countdown => "Enough players have joined. The round is starting!",
+            RoundState::SheepHeadStart => "The round has started. Sheep, get building!",
+            RoundState::Hunt => "The golems have been released!",
+            RoundState::Ended => "The round is over.",
+        };
+
+        for mut client in &mut clients {
+            client.send_chat_message(msg);
+        }
+    }
+
+    let total = match *state {
+        RoundState::Countdown => settings.countdown_secs,
+        RoundState::SheepHeadStart => settings.head_start_secs,
+        _ => return,
+    };
+
+    //Only update the action bar once per second.
+    let tick_rate = server.tick_rate().get() as i64;
+    let elapsed = clock.elapsed(&server);
+    if elapsed % tick_rate != 0 {
+        return;
+    }
+
+    let remaining = total as i64 - elapsed / tick_rate;
+    let msg = match *state {
+        RoundState::Countdown => format!("Round starts in {remaining}s"),
+        _ => format!("Golems are released in {remaining}s"),
+    };
+
+    for mut client in &mut clients {
+        client.set_action_bar(msg.clone());
+    }
+}
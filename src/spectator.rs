@@ -0,0 +1,158 @@
+use valence::{
+    command::{handler::CommandResultEvent, AddCommand},
+    command_macros::Command,
+    message::SendMessage,
+    prelude::*,
+};
+
+use crate::color::PlayerColor;
+use crate::perms::OperMode;
+use crate::round::{RoundEnded, RoundState};
+use crate::teams::{LeaveTeamEvent, Team};
+
+pub struct SpectatorPlugin;
+
+impl Plugin for SpectatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_command::<SpectateCommand>()
+            .add_systems(
+                Update,
+                (init_clients, handle_spectate_command, release_spectators),
+            )
+            .observe(spectate_enable)
+            .observe(spectate_disable);
+    }
+}
+
+/// Players watching the game instead of playing. Spectators are never on a [`Team`],
+/// so they get no disguise and don't show up on the sidebar.
+#[derive(Component, Debug, Default)]
+pub struct Spectator {
+    /// Late joiners and eliminated players go back to the lobby when the round ends.
+    until_round_end: bool,
+    //Layers made visible by spectating, removed again when they stop.
+    added_layers: Vec<Entity>,
+}
+
+impl Spectator {
+    pub fn until_round_end() -> Self {
+        Self {
+            until_round_end: true,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Command, Debug, Clone)]
+#[paths("spectate")]
+#[scopes("danny.sheeptag.spectate")]
+struct SpectateCommand;
+
+//Players that connect in the middle of a round watch it until it's over.
+fn init_clients(
+    mut clients: Query<(Entity, &mut Client), Added<Client>>,
+    round: Res<RoundState>,
+    mut commands: Commands,
+) {
+    if !round.is_running() {
+        return;
+    }
+
+    for (entity, mut client) in &mut clients {
+        commands.entity(entity).insert(Spectator::until_round_end());
+        client.send_chat_message(
+            "A round is in progress. You can spectate until it ends, then /join to play.",
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn handle_spectate_command(
+    mut events: EventReader<CommandResultEvent<SpectateCommand>>,
+    mut clients: Query<(&mut Client, Option<(&Team, &PlayerColor)>, Has<Spectator>)>,
+    mut leave: EventWriter<LeaveTeamEvent>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        let Ok((mut client, current, spectating)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+
+        if spectating {
+            commands.entity(event.executor).remove::<Spectator>();
+            client.send_chat_message("You are no longer spectating.");
+            continue;
+        }
+
+        if let Some((&team, &color)) = current {
+            leave.send(LeaveTeamEvent {
+                entity: event.executor,
+                team,
+                color,
+            });
+        }
+
+        commands.entity(event.executor).insert(Spectator::default());
+        client.send_chat_message("You are now spectating. Use /spectate again to stop.");
+    }
+}
+
+fn release_spectators(
+    mut events: EventReader<RoundEnded>,
+    mut clients: Query<(Entity, &mut Client, &Spectator)>,
+    mut commands: Commands,
+) {
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+
+    for (entity, mut client, spectator) in &mut clients {
+        if spectator.until_round_end {
+            commands.entity(entity).remove::<Spectator>();
+            client.send_chat_message("The round is over. Use /join to play the next one!");
+        }
+    }
+}
+
+//Spectators can see every entity layer, so both teams' clones and every arena are visible.
+fn spectate_enable(
+    trigger: Trigger<OnInsert, Spectator>,
+    mut clients: Query<(&mut Spectator, &mut GameMode, &mut VisibleEntityLayers)>,
+    layers: Query<Entity, With<EntityLayer>>,
+) {
+    let ent = trigger.entity();
+    if let Ok((mut spectator, mut gm, mut visible)) = clients.get_mut(ent) {
+        *gm = GameMode::Spectator;
+
+        for layer in &layers {
+            if visible.0.insert(layer) {
+                spectator.added_layers.push(layer);
+            }
+        }
+    }
+}
+
+//Players in op mode go back to building in creative, everyone else to survival.
+fn spectate_disable(
+    trigger: Trigger<OnRemove, Spectator>,
+    mut clients: Query<(
+        &Spectator,
+        &mut GameMode,
+        &mut VisibleEntityLayers,
+        Has<OperMode>,
+    )>,
+) {
+    let ent = trigger.entity();
+    if let Ok((spectator, mut gm, mut visible, is_op)) = clients.get_mut(ent) {
+        *gm = if is_op {
+            GameMode::Creative
+        } else {
+            GameMode::Survival
+        };
+
+        for layer in &spectator.added_layers {
+            visible.0.remove(layer);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use valence::{message::SendMessage, prelude::*, title::SetTitle};
+
+use crate::color::PlayerColor;
+use crate::round::{secs_to_ticks, RoundState};
+use crate::spectator::Spectator;
+use crate::tagging::{SheepTagged, TagConsequence, TagSettings, Tagged};
+use crate::teams::{LeaveTeamEvent, Team};
+
+pub struct SpiritPlugin;
+
+impl Plugin for SpiritPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RescueSettings>()
+            .add_event::<SheepRescued>()
+            .add_systems(
+                Update,
+                (
+                    become_spirit,
+                    rescue_spirits,
+                    eliminate_spirits,
+                    clear_spirits,
+                ),
+            );
+    }
+}
+
+/// A tagged sheep waiting to be rescued. Spirits can move around, but can't build.
+#[derive(Component, Debug)]
+pub struct Spirit {
+    /// Ticks a living teammate has spent next to this spirit without interruption.
+    rescue_progress: i64,
+    /// The server tick the sheep was tagged on.
+    tagged_at: i64,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct RescueSettings {
+    /// How close a living sheep has to stand to a spirit to rescue it.
+    pub radius: f64,
+    pub rescue_secs: u32,
+    /// Spirits that haven't been rescued after this long are eliminated. They leave
+    /// their team and spectate for the rest of the round. `None` keeps them around
+    /// until the round ends.
+    pub eliminate_secs: Option<u32>,
+}
+
+impl Default for RescueSettings {
+    fn default() -> Self {
+        Self {
+            radius: 2.0,
+            rescue_secs: 5,
+            eliminate_secs: Some(60),
+        }
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SheepRescued {
+    pub sheep: Entity,
+    pub rescuer: Entity,
+}
+
+fn become_spirit(
+    mut events: EventReader<SheepTagged>,
+    settings: Res<TagSettings>,
+    server: Res<Server>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        if settings.consequence != TagConsequence::Spirit {
+            continue;
+        }
+
+        if let Some(mut ent) = commands.get_entity(event.sheep) {
+            ent.insert(Spirit {
+                rescue_progress: 0,
+                tagged_at: server.current_tick(),
+            });
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn rescue_spirits(
+    mut spirits: Query<(Entity, &mut Client, &Position, &mut Spirit)>,
+    living: Query<(Entity, &Position, &Team), (With<Client>, Without<Tagged>)>,
+    names: Query<&Username>,
+    settings: Res<RescueSettings>,
+    round: Res<RoundState>,
+    server: Res<Server>,
+    mut ew: EventWriter<SheepRescued>,
+    mut commands: Commands,
+) {
+    if !round.is_running() {
+        return;
+    }
+
+    let needed = secs_to_ticks(settings.rescue_secs, &server);
+    let tick_rate = server.tick_rate().get() as i64;
+
+    for (sheep, mut client, pos, mut spirit) in &mut spirits {
+        let rescuer = living.iter().find(|(_, other, &team)| {
+            team == Team::Sheep && other.0.distance(pos.0) <= settings.radius
+        });
+
+        let Some((rescuer, _, _)) = rescuer else {
+            if spirit.rescue_progress > 0 {
+                spirit.rescue_progress = 0;
+                client.set_action_bar("Rescue interrupted!");
+            }
+            continue;
+        };
+
+        spirit.rescue_progress += 1;
+        if spirit.rescue_progress < needed {
+            if spirit.rescue_progress % tick_rate == 0 {
+                let left = (needed - spirit.rescue_progress) / tick_rate;
+                client.set_action_bar(format!("Being rescued... {left}s"));
+            }
+            continue;
+        }
+
+        commands.entity(sheep).remove::<(Spirit, Tagged)>();
+        client.send_chat_message("You have been rescued!");
+        if let Ok(ign) = names.get(rescuer) {
+            client.send_chat_message(format!("Thank {ign} for saving you."));
+        }
+
+        ew.send(SheepRescued { sheep, rescuer });
+    }
+}
+
+fn eliminate_spirits(
+    mut spirits: Query<(Entity, &mut Client, &Spirit, &PlayerColor), Without<Spectator>>,
+    settings: Res<RescueSettings>,
+    round: Res<RoundState>,
+    server: Res<Server>,
+    mut leave: EventWriter<LeaveTeamEvent>,
+    mut commands: Commands,
+) {
+    let Some(secs) = settings.eliminate_secs else {
+        return;
+    };
+    if !round.is_running() {
+        return;
+    }
+
+    for (sheep, mut client, spirit, &color) in &mut spirits {
+        if server.current_tick() - spirit.tagged_at < secs_to_ticks(secs, &server) {
+            continue;
+        }
+
+        leave.send(LeaveTeamEvent {
+            entity: sheep,
+            team: Team::Sheep,
+            color,
+        });
+        commands.entity(sheep).insert(Spectator::until_round_end());
+        client
+            .send_chat_message("Nobody came to rescue you. You can spectate until the round ends.");
+    }
+}
+
+fn clear_spirits(mut events: EventReader<LeaveTeamEvent>, mut commands: Commands) {
+    for event in events.read() {
+        if let Some(mut ent) = commands.get_entity(event.entity) {
+            ent.remove::<Spirit>();
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use valence::{
+    entity::active_status_effects::{ActiveStatusEffect, ActiveStatusEffects},
+    interact_entity::{EntityInteraction, InteractEntityEvent},
+    message::SendMessage,
+    prelude::*,
+    status_effects::StatusEffect,
+};
+
+use crate::color::PlayerColor;
+use crate::disguise::ClonedEntity;
+use crate::round::RoundState;
+use crate::spectator::Spectator;
+use crate::teams::{LeaveTeamEvent, Team};
+
+pub struct TaggingPlugin;
+
+impl Plugin for TaggingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TagSettings>()
+            .add_event::<SheepTagged>()
+            .add_systems(
+                Update,
+                (
+                    (resolve_attacks, apply_tag_consequences).chain(),
+                    clear_tags,
+                ),
+            );
+    }
+}
+
+/// Marks a sheep that has been tagged by a golem this round.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Tagged {
+    pub by: Entity,
+}
+
+/// What happens to a sheep after it has been tagged.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TagConsequence {
+    /// The sheep is only marked as [`Tagged`].
+    Mark,
+    /// The sheep is marked and can no longer move.
+    Freeze,
+    /// The sheep becomes a spirit that can be rescued by its teammates. Spirits that
+    /// stay unrescued for too long are eliminated, see [`RescueSettings`].
+    ///
+    /// [`RescueSettings`]: crate::spirit::RescueSettings
+    Spirit,
+    /// The sheep is taken off its team and spectates for the rest of the round.
+    Eliminate,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct TagSettings {
+    pub consequence: TagConsequence,
+    /// Broadcast every tag to all players in chat.
+    pub announce: bool,
+    /// How far golems can hit, from their eyes to the closest point of the sheep.
+    /// Vanilla allows 3 blocks, the rest makes up for lag.
+    pub reach: f64,
+}
+
+impl Default for TagSettings {
+    fn default() -> Self {
+        Self {
+            consequence: TagConsequence::Spirit,
+            announce: true,
+            reach: 4.0,
+        }
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SheepTagged {
+    pub golem: Entity,
+    pub sheep: Entity,
+}
+
+//Attacks can land on either a player's clone or the player itself,
+//so both are resolved back to the player before validating teams.
+fn resolve_attacks(
+    mut events: EventReader<InteractEntityEvent>,
+    players: Query<(&Team, &Position, Has<Tagged>), With<Client>>,
+    clones: Query<&ClonedEntity>,
+    state: Res<RoundState>,
+    settings: Res<TagSettings>,
+    mut ew: EventWriter<SheepTagged>,
+) {
+    //The same sheep may be hit by several golems in one tick.
+    let mut tagged = vec![];
+
+    for event in events.read() {
+        if event.interact != EntityInteraction::Attack || *state != RoundState::Hunt {
+            continue;
+        }
+
+        let target = clones
+            .get(event.entity)
+            .map(|clone| clone.0)
+            .unwrap_or(event.entity);
+
+        let Ok((Team::Golem, golem, _)) = players.get(event.client) else {
+            continue;
+        };
+
+        let Ok((Team::Sheep, sheep, false)) = players.get(target) else {
+            continue;
+        };
+
+        //The client picks what it hit, so modified clients could hit sheep anywhere.
+        if !within_reach(golem.0, sheep.0, settings.reach) {
+            continue;
+        }
+
+        if tagged.contains(&target) {
+            continue;
+        }
+        tagged.push(target);
+
+        ew.send(SheepTagged {
+            golem: event.client,
+            sheep: target,
+        });
+    }
+}
+
+fn within_reach(golem: DVec3, sheep: DVec3, reach: f64) -> bool {
+    const EYE_HEIGHT: f64 = 1.62;
+    //Sheep are 0.9 blocks wide and 1.3 blocks tall.
+    const HALF_WIDTH: f64 = 0.45;
+    const HEIGHT: f64 = 1.3;
+
+    let eyes = golem + DVec3::new(0.0, EYE_HEIGHT, 0.0);
+    let min = sheep - DVec3::new(HALF_WIDTH, 0.0, HALF_WIDTH);
+    let max = sheep + DVec3::new(HALF_WIDTH, HEIGHT, HALF_WIDTH);
+
+    eyes.clamp(min, max).distance(eyes) <= reach
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_tag_consequences(
+    mut events: EventReader<SheepTagged>,
+    mut clients: Query<&mut Client>,
+    mut statuses: Query<&mut ActiveStatusEffects>,
+    colors: Query<&PlayerColor>,
+    names: Query<&Username>,
+    settings: Res<TagSettings>,
+    mut leave: EventWriter<LeaveTeamEvent>,
+    mut commands: Commands,
+) {
+    for SheepTagged { golem, sheep } in events.read() {
+        let Some(mut ent) = commands.get_entity(*sheep) else {
+            continue;
+        };
+
+        match settings.consequence {
+            TagConsequence::Eliminate => {
+                if let Ok(&color) = colors.get(*sheep) {
+                    leave.send(LeaveTeamEvent {
+                        entity: *sheep,
+                        team: Team::Sheep,
+                        color,
+                    });
+                }
+                ent.insert(Spectator::until_round_end());
+            }
+            TagConsequence::Freeze => {
+                ent.insert(Tagged { by: *golem });
+                if let Ok(mut statuses) = statuses.get_mut(*sheep) {
+                    freeze(&mut statuses);
+                }
+            }
+            TagConsequence::Mark | TagConsequence::Spirit => {
+                ent.insert(Tagged { by: *golem });
+            }
+        }
+
+        if let Ok(mut client) = clients.get_mut(*sheep) {
+            client.send_chat_message("You have been tagged!");
+        }
+
+        if !settings.announce {
+            continue;
+        }
+
+        let (Ok(golem_ign), Ok(sheep_ign)) = (names.get(*golem), names.get(*sheep)) else {
+            continue;
+        };
+
+        for mut client in &mut clients {
+            client.send_chat_message(format!("{golem_ign} tagged {sheep_ign}!"));
+        }
+    }
+}
+
+fn clear_tags(
+    mut events: EventReader<LeaveTeamEvent>,
+    mut statuses: Query<&mut ActiveStatusEffects, With<Tagged>>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        let Ok(mut statuses) = statuses.get_mut(event.entity) else {
+            continue;
+        };
+
+        unfreeze(&mut statuses);
+        commands.entity(event.entity).remove::<Tagged>();
+    }
+}
+
+fn freeze(statuses: &mut ActiveStatusEffects) {
+    statuses.apply(
+        ActiveStatusEffect::from_effect(StatusEffect::Slowness)
+            .with_infinite()
+            .with_ambient(true)
+            .with_show_particles(false)
+            .with_amplifier(127),
+    );
+}
+
+fn unfreeze(statuses: &mut ActiveStatusEffects) {
+    statuses.remove(StatusEffect::Slowness);
+}
@@ -14,18 +14,31 @@ use valence::{
 };
 
 use crate::color::{ColorMap, PlayerColor};
+use crate::round::{RoundSettings, RoundState, RoundStateChanged};
+use crate::spectator::Spectator;
 
 pub struct TeamPlugin;
 
 impl Plugin for TeamPlugin {
     fn build(&self, app: &mut App) {
         app.add_command::<JoinTeamCommand>()
+            .add_command::<LeaveTeamCommand>()
             .add_event::<JoinTeamEvent>()
+            .add_event::<LeaveTeamEvent>()
             .insert_resource(ColorMap::new())
+            .init_resource::<TeamSettings>()
             .add_systems(Startup, setup)
             .add_systems(
                 Update,
-                (handle_join_command, init_clients, remove_player_color),
+                (
+                    handle_join_command,
+                    handle_leave_command,
+                    handle_leave_team,
+                    balance_teams,
+                    join_pending,
+                    init_clients,
+                    remove_player_color,
+                ),
             );
     }
 }
@@ -36,6 +49,42 @@ pub enum Team {
     Golem,
 }
 
+/// The ratio of sheep to golems that auto-assignment and balancing aim for.
+#[derive(Resource, Debug, Clone)]
+pub struct TeamSettings {
+    pub sheep_ratio: u32,
+    pub golem_ratio: u32,
+}
+
+impl Default for TeamSettings {
+    fn default() -> Self {
+        Self {
+            sheep_ratio: 3,
+            golem_ratio: 1,
+        }
+    }
+}
+
+impl TeamSettings {
+    /// The team that keeps the teams closest to the ratio when one more player joins.
+    fn preferred_team(&self, sheep: usize, golems: usize) -> Team {
+        if golems < self.target_golems(sheep + golems + 1) {
+            Team::Golem
+        } else {
+            Team::Sheep
+        }
+    }
+
+    /// How many of `total` players should be golems. Both teams keep at least one
+    /// player whenever there are enough players for that.
+    fn target_golems(&self, total: usize) -> usize {
+        let ratio_total = (self.sheep_ratio + self.golem_ratio).max(1) as usize;
+        let golems = (total * self.golem_ratio as usize + ratio_total / 2) / ratio_total;
+
+        golems.clamp(total.min(1), total.saturating_sub(1).max(1))
+    }
+}
+
 impl FromStr for Team {
     type Err = String;
 
@@ -48,6 +97,15 @@ impl FromStr for Team {
     }
 }
 
+impl Team {
+    pub fn other(&self) -> Team {
+        match *self {
+            Team::Sheep => Team::Golem,
+            Team::Golem => Team::Sheep,
+        }
+    }
+}
+
 impl CommandArg for Team {
     fn parse_arg(input: &mut ParseInput) -> Result<Self, CommandArgParseError> {
         input.skip_whitespace();
@@ -72,6 +130,11 @@ struct JoinTeamCommand {
     team: Option<Team>,
 }
 
+#[derive(Command, Debug, Clone)]
+#[paths("leave")]
+#[scopes("danny.sheeptag.leave")]
+struct LeaveTeamCommand;
+
 #[derive(Event, Clone, Debug)]
 pub struct JoinTeamEvent {
     pub entity: Entity,
@@ -79,40 +142,320 @@ pub struct JoinTeamEvent {
     pub color: PlayerColor,
 }
 
+/// Sent to take a player off their team. Every plugin that attaches
+/// team state to a player cleans it up in response.
+#[derive(Event, Clone, Debug)]
+pub struct LeaveTeamEvent {
+    pub entity: Entity,
+    pub team: Team,
+    pub color: PlayerColor,
+}
+
+/// Moves a player to another team. The player is taken off their team first,
+/// and rejoins once every [`LeaveTeamEvent`] handler has seen them leave.
+#[derive(Component, Debug, Clone, Copy)]
+struct PendingJoin {
+    team: Team,
+    //Event readers may not see the LeaveTeamEvent until the tick after it was sent.
+    not_before: i64,
+}
+
+fn join_team(
+    entity: Entity,
+    team: Team,
+    client: &mut Client,
+    colors: &mut ColorMap,
+    commands: &mut Commands,
+    ew: &mut EventWriter<JoinTeamEvent>,
+) -> bool {
+    //Joining a team directly cancels whatever move was still pending.
+    commands.entity(entity).remove::<PendingJoin>();
+
+    let Ok(color) = colors.register_player(entity, &team) else {
+        client.send_chat_message(format!("Sorry, the team {team:?} is full."));
+        return false;
+    };
+
+    commands.entity(entity).insert((team, color));
+    client.send_chat_message(format!("You are now a {color:?} {team:?}."));
+    ew.send(JoinTeamEvent {
+        entity,
+        team,
+        color,
+    });
+
+    true
+}
+
+//How many players are on each team. Players on their way to another team already count
+//towards it.
+fn team_sizes(teams: &Query<&Team>, pending: &Query<&PendingJoin>) -> (usize, usize) {
+    let count = |team: Team| {
+        teams.iter().filter(|&&other| other == team).count()
+            + pending.iter().filter(|other| other.team == team).count()
+    };
+
+    (count(Team::Sheep), count(Team::Golem))
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn handle_join_command(
     mut events: EventReader<CommandResultEvent<JoinTeamCommand>>,
-    mut clients: Query<&mut Client, Without<Team>>,
+    mut clients: Query<(&mut Client, Option<(&Team, &PlayerColor)>, Has<Spectator>)>,
+    teams: Query<&Team>,
+    pending: Query<&PendingJoin>,
     mut ew: EventWriter<JoinTeamEvent>,
+    mut leave: EventWriter<LeaveTeamEvent>,
     mut commands: Commands,
     mut colors: ResMut<ColorMap>,
+    settings: Res<TeamSettings>,
+    round: Res<RoundState>,
+    server: Res<Server>,
 ) {
     for event in events.read() {
-        let Ok(mut client) = clients.get_mut(event.executor) else {
-            return;
+        let Ok((mut client, current, spectating)) = clients.get_mut(event.executor) else {
+            continue;
         };
 
-        match event.result.team {
-            Some(team) => {
-                let Ok(color) = colors.register_player(event.executor, &team) else {
-                    client.send_chat_message(format!("Sorry, the team {team:?} is full."));
-                    continue;
-                };
+        let heading = pending.get(event.executor).ok().map(|pending| pending.team);
+
+        //Players already on a team, or on their way to one, are switched over instead.
+        if let Some(current_team) = current.map(|(&team, _)| team).or(heading) {
+            let Some(team) = event.result.team else {
+                client.send_chat_message(format!(
+                    "You are already a {current_team:?}. Use /join <golem|sheep> to switch teams."
+                ));
+                continue;
+            };
+
+            if team == current_team {
+                client.send_chat_message(format!("You are already a {team:?}."));
+                continue;
+            }
 
-                commands.entity(event.executor).insert((team, color));
-                client.send_chat_message(format!("You are now a {color:?} {team:?}."));
-                ew.send(JoinTeamEvent {
+            if round.is_running() {
+                client.send_chat_message("You can't switch teams during a round.");
+                continue;
+            }
+
+            if !colors.has_room(&team) {
+                client.send_chat_message(format!("Sorry, the team {team:?} is full."));
+                continue;
+            }
+
+            //Players that already left their old team only change where they're headed.
+            if let Some((&current, &color)) = current {
+                leave.send(LeaveTeamEvent {
                     entity: event.executor,
-                    team,
+                    team: current,
                     color,
                 });
             }
+            commands.entity(event.executor).insert(PendingJoin {
+                team,
+                not_before: server.current_tick() + 2,
+            });
+            continue;
+        }
+
+        //Late joiners watch the current round instead.
+        if round.is_running() {
+            if !spectating {
+                commands
+                    .entity(event.executor)
+                    .insert(Spectator::until_round_end());
+            }
+            client.send_chat_message(
+                "A round is in progress. You can spectate until it ends, then /join again.",
+            );
+            continue;
+        }
+
+        if spectating {
+            commands.entity(event.executor).remove::<Spectator>();
+        }
+
+        match event.result.team {
+            Some(team) => {
+                join_team(
+                    event.executor,
+                    team,
+                    &mut client,
+                    &mut colors,
+                    &mut commands,
+                    &mut ew,
+                );
+            }
             None => {
-                client.send_chat_message("Usage: /join <golem|sheep>");
+                let (sheep, golems) = team_sizes(&teams, &pending);
+
+                //Fall back to the other team if the preferred one is full.
+                let preferred = settings.preferred_team(sheep, golems);
+                let team = if colors.has_room(&preferred) {
+                    preferred
+                } else {
+                    preferred.other()
+                };
+
+                join_team(
+                    event.executor,
+                    team,
+                    &mut client,
+                    &mut colors,
+                    &mut commands,
+                    &mut ew,
+                );
             }
         }
     }
 }
 
+//Moves players between teams until they match the configured ratio. This happens when the
+//countdown starts, as doing it any later would leave them at their old team's spawn once the
+//round starts. Lobbies with enough players that all picked the same team would never count
+//down, so those are balanced right away.
+#[allow(clippy::too_many_arguments)]
+fn balance_teams(
+    mut changes: EventReader<RoundStateChanged>,
+    mut clients: Query<(Entity, &mut Client, &Team, &PlayerColor)>,
+    teams: Query<&Team>,
+    pending: Query<&PendingJoin>,
+    colors: Res<ColorMap>,
+    settings: Res<TeamSettings>,
+    rounds: Res<RoundSettings>,
+    round: Res<RoundState>,
+    server: Res<Server>,
+    mut leave: EventWriter<LeaveTeamEvent>,
+    mut commands: Commands,
+) {
+    let countdowns = changes
+        .read()
+        .filter(|change| change.to == RoundState::Countdown)
+        .count();
+
+    let (sheep, golems) = team_sizes(&teams, &pending);
+    let total = sheep + golems;
+    let enough_players = total >= rounds.min_sheep + rounds.min_golems;
+    let stuck = *round == RoundState::Lobby
+        && enough_players
+        && (sheep < rounds.min_sheep || golems < rounds.min_golems);
+
+    if countdowns == 0 && !stuck {
+        return;
+    }
+
+    //Rounds can't start without enough players on both teams, whatever the ratio says.
+    let target = if enough_players {
+        settings
+            .target_golems(total)
+            .clamp(rounds.min_golems, total - rounds.min_sheep)
+    } else {
+        settings.target_golems(total)
+    };
+
+    let (from, moves) = match golems.cmp(&target) {
+        std::cmp::Ordering::Less => (Team::Sheep, target - golems),
+        std::cmp::Ordering::Greater => (Team::Golem, golems - target),
+        std::cmp::Ordering::Equal => return,
+    };
+    let moves = moves.min(colors.free_colors(&from.other()));
+
+    for (entity, mut client, &team, &color) in clients
+        .iter_mut()
+        .filter(|(_, _, &team, _)| team == from)
+        .take(moves)
+    {
+        client.send_chat_message(format!(
+            "Teams were uneven, so you have been moved to the {:?} team.",
+            team.other()
+        ));
+        leave.send(LeaveTeamEvent {
+            entity,
+            team,
+            color,
+        });
+        commands.entity(entity).insert(PendingJoin {
+            team: team.other(),
+            not_before: server.current_tick() + 2,
+        });
+    }
+}
+
+fn join_pending(
+    mut clients: Query<(Entity, &mut Client, &PendingJoin), Without<Team>>,
+    mut ew: EventWriter<JoinTeamEvent>,
+    mut commands: Commands,
+    mut colors: ResMut<ColorMap>,
+    server: Res<Server>,
+) {
+    for (entity, mut client, pending) in &mut clients {
+        if server.current_tick() < pending.not_before {
+            continue;
+        }
+
+        join_team(
+            entity,
+            pending.team,
+            &mut client,
+            &mut colors,
+            &mut commands,
+            &mut ew,
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn handle_leave_command(
+    mut events: EventReader<CommandResultEvent<LeaveTeamCommand>>,
+    mut clients: Query<(
+        &mut Client,
+        Option<(&Team, &PlayerColor)>,
+        Option<&PendingJoin>,
+    )>,
+    mut leave: EventWriter<LeaveTeamEvent>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        let Ok((mut client, current, pending)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+
+        //Players on their way to another team already left their old one, so that move
+        //is all there is to cancel.
+        if let Some(pending) = pending {
+            commands.entity(event.executor).remove::<PendingJoin>();
+            client.send_chat_message(format!("You are no longer a {:?}.", pending.team));
+            continue;
+        }
+
+        let Some((&team, &color)) = current else {
+            client.send_chat_message("You are not on a team.");
+            continue;
+        };
+
+        leave.send(LeaveTeamEvent {
+            entity: event.executor,
+            team,
+            color,
+        });
+        client.send_chat_message(format!("You are no longer a {team:?}."));
+    }
+}
+
+fn handle_leave_team(
+    mut events: EventReader<LeaveTeamEvent>,
+    mut colors: ResMut<ColorMap>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        colors.unregister_player(event.entity);
+
+        if let Some(mut ent) = commands.get_entity(event.entity) {
+            ent.remove::<(Team, PlayerColor)>();
+        }
+    }
+}
+
 fn setup(mut cmd_scopes: ResMut<CommandScopeRegistry>) {
     cmd_scopes.add_scope("danny.sheeptag");
 }